@@ -0,0 +1,205 @@
+//! Runs the 2020 solutions from the command line, without `cargo aoc`.
+//!
+//! ```shell
+//! aoc2020 all
+//! aoc2020 12 2 --input my-input.txt
+//! cat day8.txt | aoc2020 8 --input -
+//! ```
+
+use rust_2020::runner::{self, Failure};
+use rust_2020::ParseMode;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+const USAGE: &str = "\
+Usage: aoc2020 <day|all> [part] [options]
+
+Arguments:
+  <day>                 1-25, or \"all\" to run every day
+  [part]                1 or 2, both parts run if left out
+
+Options:
+  -i, --input <path>    input file, or \"-\" to read stdin
+                        (default: <input dir>/day<N>.txt)
+  --input-dir <dir>     where to find inputs by day (default: input/2020)
+  --lenient             skip input lines that can't be parsed, rather than failing
+  -h, --help            show this message
+
+Exit codes:
+  0  every part was solved
+  1  a part couldn't find an answer
+  2  the arguments weren't understood
+  3  an input couldn't be read
+  4  an input couldn't be parsed";
+
+/// Ordered so that the worst outcome of several runs is the largest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Solved = 0,
+    NoAnswer = 1,
+    Usage = 2,
+    Unreadable = 3,
+    Unparseable = 4,
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+struct Config {
+    days: Vec<u32>,
+    parts: Vec<u32>,
+    input: Option<Input>,
+    input_dir: PathBuf,
+    lenient: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Config, String> {
+    let mut config = Config {
+        days: vec![],
+        parts: vec![1, 2],
+        input: None,
+        input_dir: PathBuf::from("input/2020"),
+        lenient: false,
+    };
+    let mut positional = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-i" | "--input" => {
+                let path = args.next().ok_or("--input needs a path")?;
+                config.input = Some(match path.as_str() {
+                    "-" => Input::Stdin,
+                    _ => Input::File(PathBuf::from(path)),
+                });
+            }
+            "--input-dir" => {
+                config.input_dir = PathBuf::from(args.next().ok_or("--input-dir needs a path")?);
+            }
+            "--lenient" => config.lenient = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option {:?}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+    let mut positional = positional.into_iter();
+    config.days = match positional.next().as_deref() {
+        Some("all") => (1..=25).collect(),
+        Some(day) => match day.parse() {
+            Ok(day) if (1..=25).contains(&day) => vec![day],
+            _ => return Err(format!("{:?} isn't a day from 1 to 25", day)),
+        },
+        None => return Err("which day should be run?".to_string()),
+    };
+    if let Some(part) = positional.next() {
+        config.parts = match part.as_str() {
+            "1" => vec![1],
+            "2" => vec![2],
+            _ => return Err(format!("{:?} isn't part 1 or 2", part)),
+        };
+    }
+    if let Some(extra) = positional.next() {
+        return Err(format!("unexpected argument {:?}", extra));
+    }
+    if let ([day], [part]) = (config.days.as_slice(), config.parts.as_slice()) {
+        if runner::solver(*day, *part).is_none() {
+            return Err(format!("day {} has no part {}", day, part));
+        }
+    }
+    if config.days.len() > 1 && config.input.is_some() {
+        return Err("--input can only be used with a single day".to_string());
+    }
+    Ok(config)
+}
+
+/// Reads the input for the day, `None` if there is no default input for it.
+fn read_input(config: &Config, day: u32) -> Option<io::Result<String>> {
+    let path = match &config.input {
+        Some(Input::Stdin) => {
+            let mut input = String::new();
+            return Some(io::stdin().read_to_string(&mut input).map(|_| input));
+        }
+        Some(Input::File(path)) => path.clone(),
+        None => config.input_dir.join(format!("day{}.txt", day)),
+    };
+    // running everything skips the days that haven't been downloaded
+    if config.input.is_none() && config.days.len() > 1 && !path.exists() {
+        return None;
+    }
+    Some(
+        fs::read_to_string(&path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+    )
+}
+
+fn run_day(config: &Config, day: u32) -> Status {
+    let input = match read_input(config, day) {
+        Some(Ok(input)) => input,
+        Some(Err(e)) => {
+            eprintln!("day {:>2}: couldn't read input, {}", day, e);
+            return Status::Unreadable;
+        }
+        None => {
+            println!("day {:>2}: no input, skipped", day);
+            return Status::Solved;
+        }
+    };
+    let mut status = Status::Solved;
+    for part in config.parts.iter() {
+        // day 25 only has one part
+        let solver = match runner::solver(day, *part) {
+            Some(solver) => solver,
+            None => continue,
+        };
+        match runner::run(solver, &input) {
+            Ok(outcome) => println!(
+                "day {:>2} part {}: {}  (parse {:?}, solve {:?})",
+                day, part, outcome.answer, outcome.parse_time, outcome.solve_time
+            ),
+            Err(failure) => {
+                eprintln!("day {:>2} part {}: {}", day, part, failure);
+                status = status.max(match failure {
+                    Failure::Parse(_) => Status::Unparseable,
+                    Failure::Solve(_) => Status::NoAnswer,
+                });
+            }
+        }
+    }
+    status
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return Status::Solved.into();
+    }
+    let config = match parse_args(args.into_iter()) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return Status::Usage.into();
+        }
+    };
+    if config.lenient {
+        ParseMode::set_current(ParseMode::Lenient);
+    }
+    let mut status = Status::Solved;
+    let start = Instant::now();
+    for day in config.days.iter() {
+        status = status.max(run_day(&config, *day));
+    }
+    if config.days.len() > 1 {
+        println!("total: {:?}", start.elapsed());
+    }
+    status.into()
+}
@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+/// Why `assign` couldn't give every row its own column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssignmentError {
+    /// However the columns are shared out, these rows are left without one.
+    NoAssignment { unassigned: Vec<usize> },
+    /// There is more than one way, here are two of them.
+    Ambiguous {
+        first: Vec<usize>,
+        second: Vec<usize>,
+    },
+}
+
+impl fmt::Display for AssignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssignmentError::NoAssignment { unassigned } => {
+                write!(
+                    f,
+                    "no assignment, rows {:?} can't all get a column",
+                    unassigned
+                )
+            }
+            AssignmentError::Ambiguous { first, second } => write!(
+                f,
+                "more than one assignment, {:?} and {:?} both work",
+                first, second
+            ),
+        }
+    }
+}
+
+impl Error for AssignmentError {}
+
+/// A maximum matching between rows and columns, found with Hopcroft–Karp.
+///
+/// `candidates[row]` lists the columns that row may be given, all of them below `columns`.
+/// Each row gets at most one column and each column at most one row.
+pub fn maximum_matching(candidates: &[Vec<usize>], columns: usize) -> Vec<Option<usize>> {
+    let mut matching = Matching::new(candidates.len(), columns);
+    while matching.layer(candidates) {
+        for row in 0..candidates.len() {
+            if matching.row_to_column[row].is_none() {
+                matching.augment(candidates, row);
+            }
+        }
+    }
+    matching.row_to_column
+}
+
+/// The one way of giving every row a different column, as the column of each row.
+pub fn assign(candidates: &[Vec<usize>], columns: usize) -> Result<Vec<usize>, AssignmentError> {
+    let matching = maximum_matching(candidates, columns);
+    let unassigned: Vec<usize> = (0..candidates.len())
+        .filter(|row| matching[*row].is_none())
+        .collect();
+    if !unassigned.is_empty() {
+        return Err(AssignmentError::NoAssignment { unassigned });
+    }
+    let first: Vec<usize> = matching.into_iter().flatten().collect();
+    // Any other assignment gives some row a different column. Taking that row's
+    // column away leaves it the only row without one, so if another assignment
+    // exists there's an augmenting path from that row that doesn't go straight back.
+    for (row, &column) in first.iter().enumerate() {
+        let mut other = Matching::from_assignment(&first, columns);
+        other.row_to_column[row] = None;
+        other.column_to_row[column] = None;
+        let mut visited = vec![false; columns];
+        if other.search(candidates, row, (row, column), &mut visited) {
+            let second = other.row_to_column.into_iter().flatten().collect();
+            return Err(AssignmentError::Ambiguous { first, second });
+        }
+    }
+    Ok(first)
+}
+
+/// A partial matching, plus the breadth first layers Hopcroft–Karp searches along.
+struct Matching {
+    row_to_column: Vec<Option<usize>>,
+    column_to_row: Vec<Option<usize>>,
+    /// Distance of each row from a free row in the alternating layer graph.
+    distance: Vec<Option<usize>>,
+    /// The length of the shortest augmenting paths, which are the only ones
+    /// used in a phase.
+    shortest: Option<usize>,
+}
+
+impl Matching {
+    fn new(rows: usize, columns: usize) -> Self {
+        Matching {
+            row_to_column: vec![None; rows],
+            column_to_row: vec![None; columns],
+            distance: vec![None; rows],
+            shortest: None,
+        }
+    }
+
+    fn from_assignment(assignment: &[usize], columns: usize) -> Self {
+        let mut matching = Matching::new(assignment.len(), columns);
+        for (row, &column) in assignment.iter().enumerate() {
+            matching.row_to_column[row] = Some(column);
+            matching.column_to_row[column] = Some(row);
+        }
+        matching
+    }
+
+    /// Works out the layers from the free rows, returning whether any free column is reachable.
+    fn layer(&mut self, candidates: &[Vec<usize>]) -> bool {
+        let mut queue = VecDeque::new();
+        for row in 0..self.row_to_column.len() {
+            self.distance[row] = match self.row_to_column[row] {
+                None => {
+                    queue.push_back(row);
+                    Some(0)
+                }
+                Some(_) => None,
+            };
+        }
+        self.shortest = None;
+        while let Some(row) = queue.pop_front() {
+            let next = self.distance[row].map(|d| d + 1);
+            // anything further away than a free column can't be on a shortest path
+            if self.shortest.is_some() && next > self.shortest {
+                continue;
+            }
+            for &column in &candidates[row] {
+                match self.column_to_row[column] {
+                    None => self.shortest = self.shortest.or(next),
+                    Some(other) if self.distance[other].is_none() => {
+                        self.distance[other] = next;
+                        queue.push_back(other);
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        self.shortest.is_some()
+    }
+
+    /// Looks for a shortest augmenting path from the free `row` along the layers, and flips it.
+    fn augment(&mut self, candidates: &[Vec<usize>], row: usize) -> bool {
+        let next = self.distance[row].map(|d| d + 1);
+        for &column in &candidates[row] {
+            let free = match self.column_to_row[column] {
+                None => next == self.shortest,
+                Some(other) => self.distance[other] == next && self.augment(candidates, other),
+            };
+            if free {
+                self.row_to_column[row] = Some(column);
+                self.column_to_row[column] = Some(row);
+                return true;
+            }
+        }
+        // a dead end, don't come this way again in this phase
+        self.distance[row] = None;
+        false
+    }
+
+    /// Looks for any augmenting path from the free `row` that doesn't use the
+    /// `excluded` (row, column) edge or `visited` columns, and flips it.
+    fn search(
+        &mut self,
+        candidates: &[Vec<usize>],
+        row: usize,
+        excluded: (usize, usize),
+        visited: &mut [bool],
+    ) -> bool {
+        for &column in &candidates[row] {
+            if visited[column] || (row, column) == excluded {
+                continue;
+            }
+            visited[column] = true;
+            let free = match self.column_to_row[column] {
+                None => true,
+                Some(other) => self.search(candidates, other, excluded, visited),
+            };
+            if free {
+                self.row_to_column[row] = Some(column);
+                self.column_to_row[column] = Some(row);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn unique_assignment() {
+        // the part 2 example, row 0 can only go in column 1 and so on
+        let candidates = vec![vec![1, 2], vec![0, 1, 2], vec![2]];
+        assert_eq!(assign(&candidates, 3), Ok(vec![1, 0, 2]));
+        // spare columns are fine, as long as there's only one way to fill the rows
+        assert_eq!(assign(&[vec![3], vec![3, 0]], 4), Ok(vec![3, 0]));
+        assert_eq!(assign(&[], 2), Ok(vec![]));
+    }
+
+    #[test]
+    fn impossible_assignment() {
+        let candidates = vec![vec![0], vec![0], vec![1, 2]];
+        assert_eq!(
+            assign(&candidates, 3),
+            Err(AssignmentError::NoAssignment {
+                unassigned: vec![1]
+            })
+        );
+        assert_eq!(maximum_matching(&candidates, 3)[0], Some(0));
+    }
+
+    #[test]
+    fn ambiguous_assignment() {
+        let candidates = vec![vec![0, 1], vec![0, 1], vec![2]];
+        match assign(&candidates, 3) {
+            Err(AssignmentError::Ambiguous { first, second }) => {
+                assert_ne!(first, second);
+                assert_eq!(first[2], 2);
+                assert_eq!(second[2], 2);
+            }
+            result => panic!("expected two assignments, got {:?}", result),
+        }
+        // a row could move to a spare column
+        assert!(matches!(
+            assign(&[vec![0, 1]], 2),
+            Err(AssignmentError::Ambiguous { .. })
+        ));
+    }
+
+    #[test]
+    fn needs_augmenting_paths() {
+        // greedily giving each row its first candidate gets stuck
+        let candidates = vec![vec![0, 1], vec![0], vec![1, 2, 3], vec![2], vec![3, 4]];
+        let matching = maximum_matching(&candidates, 5);
+        assert!(matching.iter().all(Option::is_some));
+        assert_eq!(assign(&candidates, 5), Ok(vec![1, 0, 3, 2, 4]));
+    }
+
+    /// Every way of giving each row from `row` on a different unused column.
+    fn all_assignments(
+        candidates: &[Vec<usize>],
+        row: usize,
+        used: &mut Vec<usize>,
+        found: &mut Vec<Vec<usize>>,
+    ) {
+        if row == candidates.len() {
+            found.push(used.clone());
+            return;
+        }
+        for &column in &candidates[row] {
+            if !used.contains(&column) {
+                used.push(column);
+                all_assignments(candidates, row + 1, used, found);
+                used.pop();
+            }
+        }
+    }
+
+    /// The most rows that can be given different columns, trying every subset of rows.
+    fn brute_force_size(candidates: &[Vec<usize>]) -> usize {
+        (0u32..1 << candidates.len())
+            .filter(|subset| {
+                let rows: Vec<_> = (0..candidates.len())
+                    .filter(|r| subset & (1 << r) != 0)
+                    .map(|r| candidates[r].clone())
+                    .collect();
+                let mut found = vec![];
+                all_assignments(&rows, 0, &mut vec![], &mut found);
+                !found.is_empty()
+            })
+            .map(|subset| subset.count_ones() as usize)
+            .max()
+            .unwrap_or(0)
+    }
+
+    proptest! {
+        #[test]
+        fn agrees_with_brute_force(
+            allowed in proptest::collection::vec(proptest::collection::vec(any::<bool>(), 6), 0..6)
+        ) {
+            let candidates: Vec<Vec<usize>> = allowed
+                .iter()
+                .map(|row| (0..6).filter(|c| row[*c]).collect())
+                .collect();
+            let matching = maximum_matching(&candidates, 6);
+            for (row, column) in matching.iter().enumerate() {
+                if let Some(column) = column {
+                    prop_assert!(candidates[row].contains(column));
+                }
+            }
+            let mut columns: Vec<_> = matching.iter().flatten().collect();
+            columns.sort_unstable();
+            columns.dedup();
+            prop_assert_eq!(columns.len(), matching.iter().flatten().count());
+            prop_assert_eq!(columns.len(), brute_force_size(&candidates));
+
+            let mut found = vec![];
+            all_assignments(&candidates, 0, &mut vec![], &mut found);
+            match assign(&candidates, 6) {
+                Ok(assignment) => prop_assert_eq!(found, vec![assignment]),
+                Err(AssignmentError::NoAssignment { .. }) => prop_assert!(found.is_empty()),
+                Err(AssignmentError::Ambiguous { first, second }) => {
+                    prop_assert!(found.len() > 1);
+                    prop_assert!(found.contains(&first) && found.contains(&second));
+                    prop_assert_ne!(first, second);
+                }
+            }
+        }
+    }
+}
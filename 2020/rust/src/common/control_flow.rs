@@ -0,0 +1,320 @@
+use crate::common::vm::{HaltReason, Instruction, Machine};
+use std::collections::VecDeque;
+
+/// Which instructions of a program can lead to which, worked out without running it.
+///
+/// Nodes are instruction indexes, plus an extra `exit` node (one past the last
+/// instruction) that represents the program terminating. Jumps to anywhere
+/// else have no edge, the program would halt there.
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+#[allow(dead_code)]
+impl ControlFlowGraph {
+    pub fn new<I: Instruction>(program: &[I]) -> Self {
+        let nodes = program.len() + 1;
+        let mut successors = vec![vec![]; nodes];
+        let mut predecessors = vec![vec![]; nodes];
+        for (index, instruction) in program.iter().enumerate() {
+            for offset in instruction.successor_offsets() {
+                let target = match (index as isize).checked_add(offset) {
+                    Some(target) if target >= 0 && target < nodes as isize => target as usize,
+                    _ => continue,
+                };
+                if !successors[index].contains(&target) {
+                    successors[index].push(target);
+                    predecessors[target].push(index);
+                }
+            }
+        }
+        ControlFlowGraph {
+            successors,
+            predecessors,
+        }
+    }
+
+    /// The node for termination, one past the last instruction.
+    pub fn exit(&self) -> usize {
+        self.successors.len() - 1
+    }
+
+    pub fn successors(&self, node: usize) -> &[usize] {
+        &self.successors[node]
+    }
+
+    pub fn predecessors(&self, node: usize) -> &[usize] {
+        &self.predecessors[node]
+    }
+
+    /// Every node that can be reached from `start`, including itself.
+    pub fn reachable_from(&self, start: usize) -> Vec<bool> {
+        Self::search(&self.successors, start)
+    }
+
+    /// Every node that can reach `target`, including itself.
+    /// `can_reach(exit())` is every instruction that could lead to termination.
+    pub fn can_reach(&self, target: usize) -> Vec<bool> {
+        Self::search(&self.predecessors, target)
+    }
+
+    /// Breadth first search along `edges`.
+    fn search(edges: &[Vec<usize>], start: usize) -> Vec<bool> {
+        let mut seen = vec![false; edges.len()];
+        let mut queue = VecDeque::new();
+        seen[start] = true;
+        queue.push_back(start);
+        while let Some(node) = queue.pop_front() {
+            for next in edges[node].iter() {
+                if !seen[*next] {
+                    seen[*next] = true;
+                    queue.push_back(*next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Nodes reachable from `entry`, in reverse postorder.
+    fn reverse_postorder(&self, entry: usize) -> Vec<usize> {
+        let mut order = vec![];
+        let mut seen = vec![false; self.successors.len()];
+        // (node, index of the next successor to visit)
+        let mut stack = vec![(entry, 0)];
+        seen[entry] = true;
+        while let Some((node, next)) = stack.pop() {
+            match self.successors[node].get(next) {
+                Some(succ) => {
+                    stack.push((node, next + 1));
+                    if !seen[*succ] {
+                        seen[*succ] = true;
+                        stack.push((*succ, 0));
+                    }
+                }
+                None => order.push(node),
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// The dominator tree for the nodes reachable from `entry`.
+    ///
+    /// Uses the iterative algorithm from Cooper, Harvey and Kennedy's
+    /// "A Simple, Fast Dominance Algorithm".
+    pub fn dominators(&self, entry: usize) -> Dominators {
+        let order = self.reverse_postorder(entry);
+        let mut rank = vec![usize::MAX; self.successors.len()];
+        for (i, node) in order.iter().enumerate() {
+            rank[*node] = i;
+        }
+        let mut idom: Vec<Option<usize>> = vec![None; self.successors.len()];
+        idom[entry] = Some(entry);
+        let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while rank[a] > rank[b] {
+                    a = idom[a].unwrap();
+                }
+                while rank[b] > rank[a] {
+                    b = idom[b].unwrap();
+                }
+            }
+            a
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in order.iter().skip(1) {
+                let new_idom = self.predecessors[*node]
+                    .iter()
+                    .filter(|p| idom[**p].is_some())
+                    .fold(None, |acc, p| match acc {
+                        None => Some(*p),
+                        Some(acc) => Some(intersect(&idom, acc, *p)),
+                    });
+                if new_idom.is_some() && idom[*node] != new_idom {
+                    idom[*node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        Dominators { entry, idom }
+    }
+}
+
+/// Which nodes must be passed through to get to each node, see `ControlFlowGraph::dominators`.
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    entry: usize,
+    idom: Vec<Option<usize>>,
+}
+
+#[allow(dead_code)]
+impl Dominators {
+    /// The closest node that every path from the entry to `node` goes through.
+    /// `None` for the entry itself, and for nodes that can't be reached.
+    pub fn immediate_dominator(&self, node: usize) -> Option<usize> {
+        if node == self.entry {
+            return None;
+        }
+        self.idom[node]
+    }
+
+    /// Does every path from the entry to `b` go through `a`?
+    /// Every reachable node dominates itself.
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        if self.idom[b].is_none() {
+            return false;
+        }
+        let mut node = b;
+        loop {
+            if node == a {
+                return true;
+            }
+            match self.immediate_dominator(node) {
+                Some(next) => node = next,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// The single instruction change that makes the program terminate, along with
+/// the replacement instruction. `patch` gives the replacement to try for an
+/// instruction, if it can be changed at all.
+///
+/// Only instructions on the path the program actually takes can make a difference,
+/// and a replacement only helps if it jumps somewhere that can reach the exit.
+/// When every instruction has a single successor that means each candidate is
+/// checked once, so this is linear in the program length.
+///
+/// The program has to be run to find out if a patch works, so this needs
+/// `Instruction::DETECT_LOOPS`, and gives `None` without it.
+pub fn find_patch<I, F>(program: &[I], mut patch: F) -> Option<(usize, I)>
+where
+    I: Instruction,
+    F: FnMut(&I) -> Option<I>,
+{
+    if !I::DETECT_LOOPS {
+        return None;
+    }
+    let cfg = ControlFlowGraph::new(program);
+    let terminates = cfg.can_reach(cfg.exit());
+    let mut machine = Machine::new(program.to_vec()).with_trace();
+    machine.run();
+    let path: Vec<usize> = machine.trace()?.iter().map(|t| t.pc).collect();
+    for index in path {
+        let replacement = match patch(&program[index]) {
+            Some(replacement) => replacement,
+            None => continue,
+        };
+        let can_terminate = replacement.successor_offsets().iter().any(|offset| {
+            match (index as isize).checked_add(*offset) {
+                Some(target) if target >= 0 && target <= cfg.exit() as isize => {
+                    terminates[target as usize]
+                }
+                _ => false,
+            }
+        });
+        if !can_terminate {
+            continue;
+        }
+        // only certain for instruction sets without conditional jumps, so check
+        let mut patched = Machine::new(program.to_vec());
+        patched.patch(index, replacement);
+        if patched.run() == HaltReason::Terminated {
+            return Some((index, replacement));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Jumps by a fixed amount, `Jump(1)` is a no-op.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Jump(isize);
+
+    impl Instruction for Jump {
+        type State = ();
+        const DETECT_LOOPS: bool = true;
+        fn execute(&self, _: &mut ()) -> isize {
+            self.0
+        }
+        fn successor_offsets(&self) -> Vec<isize> {
+            vec![self.0]
+        }
+    }
+
+    /// Either moves on, or jumps by a fixed amount.
+    #[derive(Debug, Clone, Copy)]
+    struct Branch(isize);
+
+    impl Instruction for Branch {
+        type State = bool;
+        fn execute(&self, taken: &mut bool) -> isize {
+            *taken = !*taken;
+            if *taken {
+                self.0
+            } else {
+                1
+            }
+        }
+        fn successor_offsets(&self) -> Vec<isize> {
+            vec![1, self.0]
+        }
+    }
+
+    #[test]
+    fn reachability() {
+        // 0 -> 2 -> 3 (exit), 1 -> 0
+        let program = [Jump(2), Jump(-1), Jump(1)];
+        let cfg = ControlFlowGraph::new(&program);
+        assert_eq!(cfg.exit(), 3);
+        assert_eq!(cfg.predecessors(0), &[1]);
+        assert_eq!(cfg.reachable_from(0), vec![true, false, true, true]);
+        assert_eq!(cfg.can_reach(cfg.exit()), vec![true, true, true, true]);
+        assert_eq!(cfg.can_reach(1), vec![false, true, false, false]);
+        // a jump too far to fit in an isize goes nowhere
+        let cfg = ControlFlowGraph::new(&[Jump(1), Jump(isize::MAX)]);
+        assert!(cfg.successors(1).is_empty());
+    }
+
+    #[test]
+    fn dominator_tree() {
+        // 0 branches to 1 or 3, 1 and 2 rejoin at 3, then 3 to the exit
+        let program = [Branch(3), Branch(2), Branch(1), Branch(1)];
+        let cfg = ControlFlowGraph::new(&program);
+        let dom = cfg.dominators(0);
+        assert_eq!(dom.immediate_dominator(0), None);
+        assert_eq!(dom.immediate_dominator(1), Some(0));
+        assert_eq!(dom.immediate_dominator(2), Some(1));
+        assert_eq!(dom.immediate_dominator(3), Some(0));
+        assert_eq!(dom.immediate_dominator(4), Some(3));
+        assert!(dom.dominates(0, 4));
+        assert!(dom.dominates(1, 2));
+        assert!(!dom.dominates(1, 3));
+        assert!(dom.dominates(2, 2));
+    }
+
+    #[test]
+    fn unreachable_nodes_have_no_dominator() {
+        let program = [Jump(2), Jump(1), Jump(1)];
+        let dom = ControlFlowGraph::new(&program).dominators(0);
+        assert_eq!(dom.immediate_dominator(1), None);
+        assert!(!dom.dominates(0, 1));
+    }
+
+    #[test]
+    fn patch_loop() {
+        // 0 -> 1 -> 0 loops, making 1 a no-op lets it run on to the end
+        let program = [Jump(1), Jump(-1), Jump(1)];
+        let flip = |j: &Jump| if j.0 == 1 { None } else { Some(Jump(1)) };
+        assert_eq!(find_patch(&program, flip), Some((1, Jump(1))));
+        assert_eq!(find_patch(&[Jump(0)], |_| None), None);
+    }
+}
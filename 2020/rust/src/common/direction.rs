@@ -1,8 +1,106 @@
+use crate::common::parse_error::ParseError;
 use crate::common::vector2::Vector2;
+use crate::common::vector_n::Vector3;
 use num::traits::Signed;
 use num::Num;
+use std::convert::TryFrom;
 use std::fmt;
 use std::hash::Hash;
+use std::str::FromStr;
+
+/// A turn, stored as clockwise degrees in the range `0..360`.
+///
+/// Parses from the `L90`/`R270` style used by the puzzles, where `L` is
+/// anticlockwise and `R` is clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rotation {
+    degrees: i32,
+}
+
+#[allow(dead_code)]
+impl Rotation {
+    /// No rotation at all.
+    pub fn none() -> Self {
+        Self::clockwise(0)
+    }
+
+    pub fn clockwise(degrees: i32) -> Self {
+        Rotation {
+            degrees: degrees.rem_euclid(360),
+        }
+    }
+
+    pub fn anticlockwise(degrees: i32) -> Self {
+        // reduce first, negating i32::MIN would overflow
+        Self::clockwise(-degrees.rem_euclid(360))
+    }
+
+    /// Clockwise degrees, in the range `0..360`.
+    pub fn degrees(&self) -> i32 {
+        self.degrees
+    }
+
+    /// The number of clockwise turns of `step` degrees this rotation is made up of.
+    /// `None` if this isn't a whole number of steps.
+    pub fn steps(&self, step: i32) -> Option<i32> {
+        if self.degrees % step == 0 {
+            Some(self.degrees / step)
+        } else {
+            None
+        }
+    }
+
+    /// The number of clockwise 90° turns, if this is a multiple of 90°.
+    pub fn quarter_turns(&self) -> Option<i32> {
+        self.steps(90)
+    }
+
+    /// The rotation that undoes this one.
+    pub fn inverse(&self) -> Self {
+        Self::anticlockwise(self.degrees)
+    }
+
+    /// This rotation followed by `other`.
+    pub fn then(&self, other: Rotation) -> Self {
+        Self::clockwise(self.degrees + other.degrees)
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = ParseError;
+
+    /// `L` or `R` followed by a number of degrees, such as `R90` or `L270`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let shape = || ParseError::expected("'L' or 'R' followed by degrees").with_text(s);
+        let mut chars = s.chars();
+        let turn = chars.next().ok_or_else(shape)?;
+        let degrees = chars.as_str();
+        // the direction is given by the letter, so the amount can't have a sign
+        if degrees.starts_with(['+', '-']) {
+            return Err(ParseError::expected("an unsigned number of degrees")
+                .with_text(degrees)
+                .at_columns(1..s.len()));
+        }
+        let parsed: i32 = degrees.parse().map_err(|e| {
+            ParseError::from(e)
+                .with_text(degrees)
+                .at_columns(1..s.len())
+        })?;
+        match turn {
+            'L' => Ok(Rotation::anticlockwise(parsed)),
+            'R' => Ok(Rotation::clockwise(parsed)),
+            _ => Err(shape().at_columns(0..1)),
+        }
+    }
+}
+
+/// `directions` must be listed in clockwise order, evenly spaced around a full turn.
+fn rotate_within<T: Copy + PartialEq>(directions: &[T], from: T, rotation: Rotation) -> Option<T> {
+    let step = 360 / directions.len() as i32;
+    let steps = rotation.steps(step)? as usize;
+    let index = directions.iter().position(|d| *d == from)?;
+    Some(directions[(index + steps) % directions.len()])
+}
 
 /// a direction that can also represent a compass direction
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -22,7 +120,16 @@ impl fmt::Display for Direction {
     }
 }
 
+#[allow(dead_code)]
 impl Direction {
+    /// Every direction, in clockwise order.
+    pub const ALL: [Direction; 4] = [Up, Right, Down, Left];
+
+    /// Rotates by the given amount, `None` if it isn't a multiple of 90°.
+    pub fn rotated(&self, rotation: Rotation) -> Option<Self> {
+        rotate_within(&Self::ALL, *self, rotation)
+    }
+
     pub fn reversed(&self) -> Self {
         match &self {
             Up => Down,
@@ -109,3 +216,302 @@ impl Direction {
         }
     }
 }
+
+/// A direction that also includes the diagonals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(dead_code)]
+pub enum Direction8 {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl From<Direction> for Direction8 {
+    fn from(dir: Direction) -> Self {
+        match dir {
+            Up => Direction8::Up,
+            Down => Direction8::Down,
+            Left => Direction8::Left,
+            Right => Direction8::Right,
+        }
+    }
+}
+
+impl fmt::Display for Direction8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.compass())
+    }
+}
+
+#[allow(dead_code)]
+impl Direction8 {
+    /// Every direction, in clockwise order.
+    pub const ALL: [Direction8; 8] = [
+        Direction8::Up,
+        Direction8::UpRight,
+        Direction8::Right,
+        Direction8::DownRight,
+        Direction8::Down,
+        Direction8::DownLeft,
+        Direction8::Left,
+        Direction8::UpLeft,
+    ];
+
+    /// Rotates by the given amount, `None` if it isn't a multiple of 45°.
+    pub fn rotated(&self, rotation: Rotation) -> Option<Self> {
+        rotate_within(&Self::ALL, *self, rotation)
+    }
+
+    pub fn reversed(&self) -> Self {
+        self.rotated(Rotation::clockwise(180)).unwrap()
+    }
+
+    /// Rotated 45° anticlockwise.
+    pub fn rotated_left(&self) -> Self {
+        self.rotated(Rotation::anticlockwise(45)).unwrap()
+    }
+
+    /// Rotated 45° clockwise.
+    pub fn rotated_right(&self) -> Self {
+        self.rotated(Rotation::clockwise(45)).unwrap()
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        Direction::try_from(*self).is_err()
+    }
+
+    pub fn compass(&self) -> &str {
+        match self {
+            Direction8::Up => "north",
+            Direction8::UpRight => "north-east",
+            Direction8::Right => "east",
+            Direction8::DownRight => "south-east",
+            Direction8::Down => "south",
+            Direction8::DownLeft => "south-west",
+            Direction8::Left => "west",
+            Direction8::UpLeft => "north-west",
+        }
+    }
+
+    pub fn vector<N>(&self) -> Vector2<N>
+    where
+        N: Copy,
+        N: Ord,
+        N: Num,
+        N: Signed,
+    {
+        match self {
+            Direction8::Up => Up.vector(),
+            Direction8::Down => Down.vector(),
+            Direction8::Left => Left.vector(),
+            Direction8::Right => Right.vector(),
+            Direction8::UpRight => Up.vector() + Right.vector(),
+            Direction8::DownRight => Down.vector() + Right.vector(),
+            Direction8::DownLeft => Down.vector() + Left.vector(),
+            Direction8::UpLeft => Up.vector() + Left.vector(),
+        }
+    }
+}
+
+impl TryFrom<Direction8> for Direction {
+    type Error = Direction8;
+
+    /// Fails (returning the original direction) for diagonals.
+    fn try_from(dir: Direction8) -> Result<Self, Self::Error> {
+        match dir {
+            Direction8::Up => Ok(Up),
+            Direction8::Down => Ok(Down),
+            Direction8::Left => Ok(Left),
+            Direction8::Right => Ok(Right),
+            _ => Err(dir),
+        }
+    }
+}
+
+/// A direction on a hexagonal grid with "pointy-topped" hexagons,
+/// so there are neighbours to the east and west but not north and south.
+///
+/// Vectors use axial coordinates `(q, r)`, where `q` increases to the east
+/// and `r` increases to the south-east. The third cube coordinate is `s = -q - r`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(dead_code)]
+pub enum HexDirection {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+impl fmt::Display for HexDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.abbreviation())
+    }
+}
+
+#[allow(dead_code)]
+impl HexDirection {
+    /// Every direction, in clockwise order.
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::East,
+        HexDirection::SouthEast,
+        HexDirection::SouthWest,
+        HexDirection::West,
+        HexDirection::NorthWest,
+        HexDirection::NorthEast,
+    ];
+
+    /// Rotates by the given amount, `None` if it isn't a multiple of 60°.
+    pub fn rotated(&self, rotation: Rotation) -> Option<Self> {
+        rotate_within(&Self::ALL, *self, rotation)
+    }
+
+    pub fn reversed(&self) -> Self {
+        self.rotated(Rotation::clockwise(180)).unwrap()
+    }
+
+    /// e, se, sw, w, nw or ne
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            HexDirection::East => "e",
+            HexDirection::SouthEast => "se",
+            HexDirection::SouthWest => "sw",
+            HexDirection::West => "w",
+            HexDirection::NorthWest => "nw",
+            HexDirection::NorthEast => "ne",
+        }
+    }
+
+    /// The offset to the neighbour in this direction, in axial `(q, r)` coordinates.
+    pub fn axial<N>(&self) -> Vector2<N>
+    where
+        N: Copy,
+        N: Ord,
+        N: Num,
+        N: Signed,
+    {
+        let (zero, one) = (N::zero(), N::one());
+        match self {
+            HexDirection::East => Vector2::new(one, zero),
+            HexDirection::West => Vector2::new(-one, zero),
+            HexDirection::SouthEast => Vector2::new(zero, one),
+            HexDirection::NorthWest => Vector2::new(zero, -one),
+            HexDirection::NorthEast => Vector2::new(one, -one),
+            HexDirection::SouthWest => Vector2::new(-one, one),
+        }
+    }
+
+    /// The offset to the neighbour in this direction, in cube `(q, r, s)` coordinates.
+    pub fn cube<N>(&self) -> Vector3<N>
+    where
+        N: Copy,
+        N: Ord,
+        N: Num,
+        N: Signed,
+    {
+        let axial = self.axial::<N>();
+        Vector3::new([axial.x, axial.y, -axial.x - axial.y])
+    }
+
+    /// Splits a run of undelimited directions, like `esenee`, into its parts.
+    pub fn parse_sequence(s: &str) -> Result<Vec<Self>, ParseError> {
+        let mut result = vec![];
+        let mut i = 0;
+        while i < s.len() {
+            // north and south are always followed by east or west, so take 2 characters
+            let len = if s[i..].starts_with(['n', 's']) { 2 } else { 1 };
+            let part = s.get(i..i + len).unwrap_or(&s[i..]);
+            let dir = part
+                .parse()
+                .map_err(|e: ParseError| e.at_columns(i..i + part.len()))?;
+            result.push(dir);
+            i += len;
+        }
+        Ok(result)
+    }
+}
+
+impl FromStr for HexDirection {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HexDirection::ALL
+            .iter()
+            .find(|d| d.abbreviation() == s)
+            .copied()
+            .ok_or_else(|| ParseError::expected("one of e, se, sw, w, nw or ne").with_text(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rotation() {
+        assert_eq!("R90".parse::<Rotation>().unwrap().degrees(), 90);
+        assert_eq!("L90".parse::<Rotation>().unwrap().degrees(), 270);
+        assert_eq!("L450".parse::<Rotation>().unwrap().degrees(), 270);
+        assert!("X90".parse::<Rotation>().is_err());
+        assert_eq!("Rx".parse::<Rotation>().unwrap_err().columns(), Some(1..2));
+        let err = "L-2147483648".parse::<Rotation>().unwrap_err();
+        assert_eq!(err.columns(), Some(1..12));
+        assert!("R+90".parse::<Rotation>().is_err());
+        assert_eq!(Rotation::anticlockwise(i32::MIN).degrees(), 128);
+        assert_eq!(Rotation::anticlockwise(i32::MAX).degrees(), 233);
+    }
+
+    #[test]
+    fn rotate_cardinal() {
+        assert_eq!(Up.rotated(Rotation::clockwise(90)), Some(Right));
+        assert_eq!(Up.rotated(Rotation::anticlockwise(90)), Some(Left));
+        assert_eq!(Left.rotated(Rotation::clockwise(180)), Some(Right));
+        assert_eq!(Left.rotated(Rotation::clockwise(45)), None);
+    }
+
+    #[test]
+    fn rotate_diagonal() {
+        assert_eq!(
+            Direction8::Up.rotated(Rotation::clockwise(135)),
+            Some(Direction8::DownRight)
+        );
+        assert_eq!(Direction8::UpLeft.rotated_right(), Direction8::Up);
+        assert_eq!(Direction8::UpLeft.reversed(), Direction8::DownRight);
+        assert_eq!(Direction8::Up.rotated(Rotation::clockwise(60)), None);
+        assert_eq!(Direction8::DownLeft.vector::<i32>(), Vector2::new(-1, -1));
+    }
+
+    #[test]
+    fn rotate_hex() {
+        assert_eq!(
+            HexDirection::East.rotated(Rotation::clockwise(120)),
+            Some(HexDirection::SouthWest)
+        );
+        assert_eq!(HexDirection::NorthEast.reversed(), HexDirection::SouthWest);
+        assert_eq!(HexDirection::East.rotated(Rotation::clockwise(90)), None);
+    }
+
+    #[test]
+    fn hex_neighbours_sum_to_zero() {
+        let total = HexDirection::ALL
+            .iter()
+            .fold(Vector3::zero(), |acc, d| acc + d.cube::<i32>());
+        assert_eq!(total, Vector3::zero());
+        assert!(HexDirection::ALL.iter().all(|d| d.cube::<i32>().sum() == 0));
+    }
+
+    #[test]
+    fn hex_sequence() {
+        use HexDirection::*;
+        let dirs = HexDirection::parse_sequence("esenee").unwrap();
+        assert_eq!(dirs, vec![East, SouthEast, NorthEast, East]);
+        let err = HexDirection::parse_sequence("enwx").unwrap_err();
+        assert_eq!(err.columns(), Some(3..4));
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+
+/// One part of an alternative, either text to match exactly or another rule.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Symbol<N> {
+    Terminal(String),
+    Nonterminal(N),
+}
+
+/// A context-free grammar, rules named by `N` each with a list of alternatives.
+#[derive(Debug, Clone)]
+pub struct Grammar<N> {
+    rules: HashMap<N, Vec<Vec<Symbol<N>>>>,
+}
+
+/// How a string was derived, see `Grammar::parse`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree<N> {
+    Terminal(String),
+    Node {
+        rule: N,
+        /// Index into the rule's alternatives.
+        alternative: usize,
+        children: Vec<ParseTree<N>>,
+    },
+}
+
+/// An Earley item, `alternative` of `rule` matched up to `dot`, starting at `origin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item<N> {
+    rule: N,
+    alternative: usize,
+    dot: usize,
+    origin: usize,
+}
+
+#[allow(dead_code)]
+impl<N: Copy + Eq + Hash + Debug> Grammar<N> {
+    pub fn new() -> Self {
+        Grammar {
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Adds (or replaces) a rule.
+    pub fn with_rule(mut self, rule: N, alternatives: Vec<Vec<Symbol<N>>>) -> Self {
+        self.rules.insert(rule, alternatives);
+        self
+    }
+
+    /// The alternatives of `rule`, empty if it isn't defined.
+    pub fn alternatives(&self, rule: N) -> &[Vec<Symbol<N>>] {
+        self.rules.get(&rule).map_or(&[], |a| a.as_slice())
+    }
+
+    /// Rules that can match the empty string.
+    fn nullable(&self) -> HashSet<N> {
+        let mut nullable = HashSet::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (rule, alternatives) in self.rules.iter() {
+                if nullable.contains(rule) {
+                    continue;
+                }
+                let is_nullable = alternatives.iter().any(|alternative| {
+                    alternative.iter().all(|symbol| match symbol {
+                        Symbol::Terminal(t) => t.is_empty(),
+                        Symbol::Nonterminal(n) => nullable.contains(n),
+                    })
+                });
+                if is_nullable {
+                    nullable.insert(*rule);
+                    changed = true;
+                }
+            }
+        }
+        nullable
+    }
+
+    /// Runs an Earley recogniser over `input`, returning the item sets by byte
+    /// position. Positions that aren't reachable (such as within a terminal) are empty.
+    fn chart(&self, start: N, input: &str) -> Vec<HashSet<Item<N>>> {
+        let nullable = self.nullable();
+        let mut chart: Vec<HashSet<Item<N>>> = vec![HashSet::new(); input.len() + 1];
+        for alternative in 0..self.alternatives(start).len() {
+            chart[0].insert(Item {
+                rule: start,
+                alternative,
+                dot: 0,
+                origin: 0,
+            });
+        }
+        for position in 0..=input.len() {
+            let mut pending: Vec<Item<N>> = chart[position].iter().copied().collect();
+            while let Some(item) = pending.pop() {
+                let mut add = |chart: &mut Vec<HashSet<Item<N>>>, at: usize, item: Item<N>| {
+                    if chart[at].insert(item) && at == position {
+                        pending.push(item);
+                    }
+                };
+                match self.alternatives(item.rule)[item.alternative].get(item.dot) {
+                    // scan
+                    Some(Symbol::Terminal(t)) => {
+                        if input[position..].starts_with(t.as_str()) {
+                            let next = Item {
+                                dot: item.dot + 1,
+                                ..item
+                            };
+                            add(&mut chart, position + t.len(), next);
+                        }
+                    }
+                    // predict
+                    Some(Symbol::Nonterminal(n)) => {
+                        for alternative in 0..self.alternatives(*n).len() {
+                            let predicted = Item {
+                                rule: *n,
+                                alternative,
+                                dot: 0,
+                                origin: position,
+                            };
+                            add(&mut chart, position, predicted);
+                        }
+                        // Aycock and Horspool's fix, otherwise nullable rules
+                        // completed before this prediction would be missed
+                        if nullable.contains(n) {
+                            let next = Item {
+                                dot: item.dot + 1,
+                                ..item
+                            };
+                            add(&mut chart, position, next);
+                        }
+                    }
+                    // complete
+                    None => {
+                        let waiting: Vec<Item<N>> = chart[item.origin]
+                            .iter()
+                            .filter(|w| {
+                                self.alternatives(w.rule)[w.alternative].get(w.dot)
+                                    == Some(&Symbol::Nonterminal(item.rule))
+                            })
+                            .copied()
+                            .collect();
+                        for w in waiting {
+                            let next = Item {
+                                dot: w.dot + 1,
+                                ..w
+                            };
+                            add(&mut chart, position, next);
+                        }
+                    }
+                }
+            }
+        }
+        chart
+    }
+
+    /// Does all of `input` match `start`?
+    pub fn recognise(&self, start: N, input: &str) -> bool {
+        self.chart(start, input)[input.len()]
+            .iter()
+            .any(|item| item.rule == start && item.origin == 0 && self.is_complete(item))
+    }
+
+    fn is_complete(&self, item: &Item<N>) -> bool {
+        item.dot == self.alternatives(item.rule)[item.alternative].len()
+    }
+
+    /// How `input` matches `start`, showing which alternative of each rule was used.
+    /// If the input is ambiguous this is one of the possible trees.
+    pub fn parse(&self, start: N, input: &str) -> Option<ParseTree<N>> {
+        let chart = self.chart(start, input);
+        // completed[end] holds every (rule, alternative, start) that matched start..end
+        let completed: Vec<HashSet<(N, usize, usize)>> = chart
+            .iter()
+            .map(|set| {
+                set.iter()
+                    .filter(|item| self.is_complete(item))
+                    .map(|item| (item.rule, item.alternative, item.origin))
+                    .collect()
+            })
+            .collect();
+        let mut tree_builder = TreeBuilder {
+            grammar: self,
+            input,
+            completed: &completed,
+            in_progress: HashSet::new(),
+        };
+        tree_builder.rule(start, 0, input.len())
+    }
+
+    /// Every string of up to `max_len` characters that matches `start`.
+    pub fn enumerate(&self, start: N, max_len: usize) -> BTreeSet<String> {
+        // grow the strings each rule can make until nothing changes,
+        // which always ends because there are only so many short strings
+        let mut languages: HashMap<N, BTreeSet<String>> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (rule, alternatives) in self.rules.iter() {
+                let mut found = languages.get(rule).cloned().unwrap_or_default();
+                let before = found.len();
+                for alternative in alternatives {
+                    let mut partial: BTreeSet<String> = Some(String::new()).into_iter().collect();
+                    for symbol in alternative {
+                        let options: Vec<String> = match symbol {
+                            Symbol::Terminal(t) => vec![t.clone()],
+                            Symbol::Nonterminal(n) => languages
+                                .get(n)
+                                .map_or(vec![], |l| l.iter().cloned().collect()),
+                        };
+                        partial = partial
+                            .iter()
+                            .flat_map(|p| options.iter().map(move |o| format!("{}{}", p, o)))
+                            .filter(|s| s.chars().count() <= max_len)
+                            .collect();
+                    }
+                    found.extend(partial);
+                }
+                if found.len() != before {
+                    languages.insert(*rule, found);
+                    changed = true;
+                }
+            }
+        }
+        languages.remove(&start).unwrap_or_default()
+    }
+}
+
+/// Follows the completed Earley items back down to a `ParseTree`.
+struct TreeBuilder<'a, N> {
+    grammar: &'a Grammar<N>,
+    input: &'a str,
+    completed: &'a [HashSet<(N, usize, usize)>],
+    /// Spans being built, so that rules like `a: a | "x"` don't recurse forever.
+    in_progress: HashSet<(N, usize, usize)>,
+}
+
+impl<'a, N: Copy + Eq + Hash + Debug> TreeBuilder<'a, N> {
+    /// The tree for `rule` matching `start..end`.
+    fn rule(&mut self, rule: N, start: usize, end: usize) -> Option<ParseTree<N>> {
+        if !self.in_progress.insert((rule, start, end)) {
+            return None;
+        }
+        let alternatives: Vec<usize> = self.completed[end]
+            .iter()
+            .filter(|(r, _, origin)| *r == rule && *origin == start)
+            .map(|(_, alternative, _)| *alternative)
+            .collect();
+        let mut tree = None;
+        for alternative in alternatives {
+            let symbols = &self.grammar.alternatives(rule)[alternative];
+            if let Some(children) = self.sequence(symbols, start, end) {
+                tree = Some(ParseTree::Node {
+                    rule,
+                    alternative,
+                    children,
+                });
+                break;
+            }
+        }
+        self.in_progress.remove(&(rule, start, end));
+        tree
+    }
+
+    /// Trees for each of `symbols`, which together match `start..end`.
+    fn sequence(
+        &mut self,
+        symbols: &[Symbol<N>],
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<ParseTree<N>>> {
+        let (first, rest) = match symbols.split_first() {
+            Some(split) => split,
+            None if start == end => return Some(vec![]),
+            None => return None,
+        };
+        match first {
+            Symbol::Terminal(t) => {
+                if !self.input[start..end].starts_with(t.as_str()) {
+                    return None;
+                }
+                let mut children = vec![ParseTree::Terminal(t.clone())];
+                children.extend(self.sequence(rest, start + t.len(), end)?);
+                Some(children)
+            }
+            Symbol::Nonterminal(n) => {
+                // try every place the nonterminal could end
+                for middle in start..=end {
+                    let ends_here = self.completed[middle]
+                        .iter()
+                        .any(|(r, _, origin)| r == n && *origin == start);
+                    if !ends_here {
+                        continue;
+                    }
+                    let rest = match self.sequence(rest, middle, end) {
+                        Some(rest) => rest,
+                        None => continue,
+                    };
+                    if let Some(tree) = self.rule(*n, start, middle) {
+                        let mut children = vec![tree];
+                        children.extend(rest);
+                        return Some(children);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<N: Debug> ParseTree<N> {
+    fn write_indented(&self, f: &mut Formatter, depth: usize) -> fmt::Result {
+        match self {
+            ParseTree::Terminal(t) => writeln!(f, "{:indent$}{:?}", "", t, indent = depth * 2),
+            ParseTree::Node {
+                rule,
+                alternative,
+                children,
+            } => {
+                writeln!(
+                    f,
+                    "{:indent$}{:?} (alternative {})",
+                    "",
+                    rule,
+                    alternative,
+                    indent = depth * 2
+                )?;
+                for child in children {
+                    child.write_indented(f, depth + 1)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// One line per node, children indented below their rule.
+impl<N: Debug> Display for ParseTree<N> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(s: &str) -> Symbol<&'static str> {
+        Symbol::Terminal(s.to_string())
+    }
+
+    fn n(rule: &'static str) -> Symbol<&'static str> {
+        Symbol::Nonterminal(rule)
+    }
+
+    /// Balanced brackets, including none at all.
+    fn brackets() -> Grammar<&'static str> {
+        Grammar::new().with_rule("s", vec![vec![], vec![t("("), n("s"), t(")"), n("s")]])
+    }
+
+    /// Left recursive sums of "1" and "10".
+    fn sums() -> Grammar<&'static str> {
+        Grammar::new()
+            .with_rule(
+                "sum",
+                vec![vec![n("sum"), t("+"), n("num")], vec![n("num")]],
+            )
+            .with_rule("num", vec![vec![t("1")], vec![t("10")]])
+    }
+
+    #[test]
+    fn recognises() {
+        let brackets = brackets();
+        assert!(brackets.recognise("s", ""));
+        assert!(brackets.recognise("s", "(()())()"));
+        assert!(!brackets.recognise("s", "(()"));
+        let sums = sums();
+        assert!(sums.recognise("sum", "10+1+10"));
+        assert!(sums.recognise("num", "10"));
+        assert!(!sums.recognise("sum", "10+"));
+        assert!(!sums.recognise("sum", "11"));
+    }
+
+    #[test]
+    fn parse_trees() {
+        use ParseTree::*;
+        let tree = sums().parse("sum", "1+10").unwrap();
+        let num = |alternative, text: &str| Node {
+            rule: "num",
+            alternative,
+            children: vec![Terminal(text.to_string())],
+        };
+        let expected = Node {
+            rule: "sum",
+            alternative: 0,
+            children: vec![
+                Node {
+                    rule: "sum",
+                    alternative: 1,
+                    children: vec![num(0, "1")],
+                },
+                Terminal("+".to_string()),
+                num(1, "10"),
+            ],
+        };
+        assert_eq!(tree, expected);
+        assert_eq!(
+            tree.to_string(),
+            "\"sum\" (alternative 0)\n  \"sum\" (alternative 1)\n    \"num\" (alternative 0)\n      \
+             \"1\"\n  \"+\"\n  \"num\" (alternative 1)\n    \"10\"\n"
+        );
+        assert_eq!(sums().parse("sum", "1+"), None);
+        assert!(brackets().parse("s", "()()").is_some());
+    }
+
+    #[test]
+    fn enumerates() {
+        let strings: Vec<_> = brackets().enumerate("s", 4).into_iter().collect();
+        assert_eq!(strings, vec!["", "(())", "()", "()()"]);
+        let sums = sums().enumerate("sum", 4);
+        assert_eq!(sums.len(), 5);
+        assert!(sums.contains("10+1"));
+        assert!(!sums.contains("10+10"));
+    }
+}
@@ -0,0 +1,434 @@
+use crate::common::parse_error::ParseError;
+use crate::common::transform::Transform2;
+use crate::common::vector2::Vector2;
+use std::convert::TryFrom;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+/// Offsets to the 4 orthogonally adjacent cells (up, right, down, left).
+const ORTHOGONAL: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Offsets to all 8 surrounding cells, including the diagonals.
+const SURROUNDING: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+];
+
+/// A position type that can be used to look up a cell in a `Grid`.
+///
+/// Coordinates are widened to `i64` so that signed and unsigned vectors share
+/// the same bounds checking logic.
+pub trait GridIndex: Copy {
+    fn coordinates(self) -> (i64, i64);
+}
+
+macro_rules! grid_index {
+    ($($t:ty),*) => {
+        $(
+            impl GridIndex for Vector2<$t> {
+                #[inline]
+                fn coordinates(self) -> (i64, i64) {
+                    (self.x as i64, self.y as i64)
+                }
+            }
+        )*
+    };
+}
+
+grid_index!(i32, i64, isize, usize);
+
+/// A rectangular 2D grid, stored row-by-row in a flat buffer.
+///
+/// `x` is the column and `y` is the row, with `(0, 0)` in the top left
+/// corner (the first character of the input).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+#[allow(dead_code)]
+impl<T> Grid<T> {
+    /// A grid of the given size, with every cell set to `fill`.
+    pub fn new(width: usize, height: usize, fill: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    /// Wraps an existing row-by-row buffer of cells.
+    /// Returns `None` if the buffer cannot be split into rows of `width`.
+    pub fn from_cells(width: usize, cells: Vec<T>) -> Option<Self> {
+        if width == 0 {
+            return if cells.is_empty() {
+                Some(Self {
+                    width,
+                    height: 0,
+                    cells,
+                })
+            } else {
+                None
+            };
+        }
+        let height = cells.len() / width;
+        if height * width != cells.len() {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// (x: width, y: height)
+    pub fn size(&self) -> Vector2<usize> {
+        Vector2::new(self.width, self.height)
+    }
+
+    /// The size as signed coordinates, for wrapping and clamping.
+    fn bounds(&self) -> Vector2<i64> {
+        self.size()
+            .try_cast()
+            .expect("grid dimensions fit in an i64")
+    }
+
+    /// Is the position within the bounds of the grid?
+    pub fn contains<P: GridIndex>(&self, pos: P) -> bool {
+        self.offset(pos).is_some()
+    }
+
+    /// The index into `cells` of the given position, if it's in bounds.
+    fn offset<P: GridIndex>(&self, pos: P) -> Option<usize> {
+        let (x, y) = pos.coordinates();
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    fn position_of(&self, offset: usize) -> Vector2<usize> {
+        Vector2::new(offset % self.width, offset / self.width)
+    }
+
+    /// Checked lookup, `None` if the position is out of bounds.
+    pub fn get<P: GridIndex>(&self, pos: P) -> Option<&T> {
+        self.offset(pos).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut<P: GridIndex>(&mut self, pos: P) -> Option<&mut T> {
+        self.offset(pos).map(move |i| &mut self.cells[i])
+    }
+
+    /// Lookup where the grid repeats infinitely in every direction.
+    ///
+    /// Panics if the grid is empty.
+    pub fn get_wrapping<P: GridIndex>(&self, pos: P) -> &T {
+        let pos = Vector2::from_tup(pos.coordinates());
+        &self[pos.rem_euclid(&self.bounds())]
+    }
+
+    /// Lookup where out of bounds positions snap to the nearest edge cell.
+    ///
+    /// Panics if the grid is empty.
+    pub fn get_clamped<P: GridIndex>(&self, pos: P) -> &T {
+        let pos = Vector2::from_tup(pos.coordinates());
+        &self[pos.clamp_to(Vector2::zero()..=self.bounds() - Vector2::one())]
+    }
+
+    /// Sets the cell at the given position, returning `false` if it was out of bounds.
+    pub fn set<P: GridIndex>(&mut self, pos: P, value: T) -> bool {
+        match self.get_mut(pos) {
+            Some(cell) => {
+                *cell = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// All cells, row-by-row.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.cells.iter_mut()
+    }
+
+    /// All positions in the grid, row-by-row.
+    pub fn positions(&self) -> impl Iterator<Item = Vector2<usize>> {
+        let width = self.width;
+        (0..self.cells.len()).map(move |i| Vector2::new(i % width, i / width))
+    }
+
+    /// All cells along with their positions, row-by-row.
+    pub fn enumerate(&self) -> impl Iterator<Item = (Vector2<usize>, &T)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| (self.position_of(i), cell))
+    }
+
+    /// The cells of row `y`, from left to right.
+    pub fn row(&self, y: usize) -> &[T] {
+        let start = y * self.width;
+        &self.cells[start..start + self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    /// The cells of column `x`, from top to bottom.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        assert!(x < self.width, "column {} out of bounds", x);
+        self.cells.iter().skip(x).step_by(self.width)
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(move |x| self.column(x))
+    }
+
+    fn neighbours_from<P: GridIndex>(
+        &self,
+        pos: P,
+        offsets: &'static [(i64, i64)],
+    ) -> impl Iterator<Item = (Vector2<usize>, &T)> {
+        let (x, y) = pos.coordinates();
+        offsets.iter().filter_map(move |(dx, dy)| {
+            let i = self.offset(Vector2::new(x + dx, y + dy))?;
+            Some((self.position_of(i), &self.cells[i]))
+        })
+    }
+
+    /// The in-bounds cells directly above, right, below and left of `pos`.
+    pub fn neighbours4<P: GridIndex>(&self, pos: P) -> impl Iterator<Item = (Vector2<usize>, &T)> {
+        self.neighbours_from(pos, &ORTHOGONAL)
+    }
+
+    /// The in-bounds cells surrounding `pos`, including the diagonals.
+    pub fn neighbours8<P: GridIndex>(&self, pos: P) -> impl Iterator<Item = (Vector2<usize>, &T)> {
+        self.neighbours_from(pos, &SURROUNDING)
+    }
+
+    /// A new grid of the same size, with `f` applied to every cell.
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// A copy of the grid rotated and/or reflected about its centre.
+    ///
+    /// The transform acts on grid coordinates, where y increases downwards,
+    /// so a "clockwise" rotation appears anticlockwise when the grid is printed.
+    /// Panics if the transform can't be inverted, such as a scaling.
+    pub fn transformed(&self, transform: Transform2) -> Grid<T>
+    where
+        T: Clone,
+    {
+        let inverse = transform
+            .inverse()
+            .expect("only rotations and reflections can be applied to a grid");
+        // quarter turns swap the width and height
+        let swaps = transform.apply(Vector2::new(1i64, 0)).x == 0;
+        let (width, height) = if swaps {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        // work in doubled coordinates relative to the centre, so the centre of
+        // an even sized grid is still on an integer position
+        let to_centre = |v: usize, size: usize| 2 * v as i64 - (size as i64 - 1);
+        let from_centre = |v: i64, size: usize| ((v + size as i64 - 1) / 2) as usize;
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..height {
+            for x in 0..width {
+                let centred = Vector2::new(to_centre(x, width), to_centre(y, height));
+                let source = inverse.apply(centred);
+                let source = Vector2::new(
+                    from_centre(source.x, self.width),
+                    from_centre(source.y, self.height),
+                );
+                cells.push(self[source].clone());
+            }
+        }
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+}
+
+impl<T, P: GridIndex> Index<P> for Grid<T> {
+    type Output = T;
+    fn index(&self, pos: P) -> &T {
+        let i = self.offset(pos).expect("grid position out of bounds");
+        &self.cells[i]
+    }
+}
+
+impl<T, P: GridIndex> IndexMut<P> for Grid<T> {
+    fn index_mut(&mut self, pos: P) -> &mut T {
+        let i = self.offset(pos).expect("grid position out of bounds");
+        &mut self.cells[i]
+    }
+}
+
+/// Parses one cell per character, one row per line.
+/// Every row must be the same width.
+impl<T> FromStr for Grid<T>
+where
+    T: TryFrom<char>,
+    T::Error: Into<ParseError>,
+{
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut width = None;
+        let mut cells = vec![];
+        for (y, line) in s.lines().enumerate() {
+            let row_width = line.chars().count();
+            let width = *width.get_or_insert(row_width);
+            if width != row_width {
+                return Err(ParseError::expected(format!("a row of {} cells", width))
+                    .with_text(line)
+                    .at_line(y + 1));
+            }
+            for (x, c) in line.chars().enumerate() {
+                let cell = T::try_from(c).map_err(|e| {
+                    e.into()
+                        .with_text(c.to_string())
+                        .at_columns(x..x + 1)
+                        .at_line(y + 1)
+                })?;
+                cells.push(cell);
+            }
+        }
+        Ok(Grid::from_cells(width.unwrap_or(0), cells).expect("rows are all the same width"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::transform::Reflection;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Cell {
+        Open,
+        Wall,
+    }
+
+    impl TryFrom<char> for Cell {
+        type Error = ParseError;
+        fn try_from(c: char) -> Result<Self, Self::Error> {
+            match c {
+                '.' => Ok(Cell::Open),
+                '#' => Ok(Cell::Wall),
+                _ => Err(ParseError::expected("'.' or '#'")),
+            }
+        }
+    }
+
+    fn example() -> Grid<Cell> {
+        "#..\n.#.\n..#\n#.#".parse().unwrap()
+    }
+
+    #[test]
+    fn parse_dimensions() {
+        let grid = example();
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 4);
+        assert_eq!(grid[Vector2::new(1usize, 1)], Cell::Wall);
+        assert_eq!(grid[Vector2::new(2i32, 0)], Cell::Open);
+    }
+
+    #[test]
+    fn parse_rejects_bad_input() {
+        let err = "#.\n#".parse::<Grid<Cell>>().unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        let err = "..\n#x".parse::<Grid<Cell>>().unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.columns(), Some(1..2));
+        assert_eq!(err.text(), Some("x"));
+    }
+
+    #[test]
+    fn lookup_modes() {
+        let grid = example();
+        assert_eq!(grid.get(Vector2::new(-1, 0)), None);
+        assert_eq!(grid.get(Vector2::new(3, 0)), None);
+        assert_eq!(grid.get_wrapping(Vector2::new(-1, 0)), &Cell::Open);
+        assert_eq!(grid.get_wrapping(Vector2::new(4, 5)), &Cell::Wall);
+        assert_eq!(grid.get_clamped(Vector2::new(10, 10)), &Cell::Wall);
+        assert_eq!(grid.get_clamped(Vector2::new(-10, -10)), &Cell::Wall);
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let grid = example();
+        assert_eq!(grid.row(1), &[Cell::Open, Cell::Wall, Cell::Open]);
+        let column: Vec<_> = grid.column(2).copied().collect();
+        assert_eq!(column, vec![Cell::Open, Cell::Open, Cell::Wall, Cell::Wall]);
+        assert_eq!(grid.rows().count(), 4);
+        assert_eq!(grid.columns().count(), 3);
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = example();
+        assert_eq!(grid.neighbours4(Vector2::new(0, 0)).count(), 2);
+        assert_eq!(grid.neighbours8(Vector2::new(0, 0)).count(), 3);
+        assert_eq!(grid.neighbours8(Vector2::new(1, 1)).count(), 8);
+        let walls = grid
+            .neighbours8(Vector2::new(1, 2))
+            .filter(|(_, c)| **c == Cell::Wall)
+            .count();
+        assert_eq!(walls, 4);
+    }
+
+    #[test]
+    fn transformed() {
+        let grid = example();
+        let transposed = grid.transformed(Transform2::reflection(Reflection::Diagonal));
+        assert_eq!((transposed.width(), transposed.height()), (4, 3));
+        for (pos, cell) in grid.enumerate() {
+            assert_eq!(transposed[Vector2::new(pos.y, pos.x)], *cell);
+        }
+        let flipped = grid.transformed(Transform2::reflection(Reflection::YAxis));
+        assert_eq!(flipped.row(3), &[Cell::Wall, Cell::Open, Cell::Wall]);
+        assert_eq!(flipped.row(0), &[Cell::Open, Cell::Open, Cell::Wall]);
+        let orientations: Vec<_> = Transform2::ALL
+            .iter()
+            .map(|t| grid.transformed(*t).cells)
+            .collect();
+        for (i, a) in orientations.iter().enumerate() {
+            assert!(orientations[i + 1..].iter().all(|b| a != b));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use num::PrimInt;
+use std::iter::FromIterator;
+use std::ops::RangeInclusive;
+
+/// A set of integers, stored as sorted inclusive ranges.
+///
+/// The ranges never overlap or touch, ranges that would are merged into one,
+/// so `1..=3` and `4..=6` are stored as `1..=6`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    ranges: Vec<RangeInclusive<T>>,
+}
+
+impl<T: PrimInt> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { ranges: vec![] }
+    }
+}
+
+impl<T: PrimInt> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The merged ranges, in order.
+    #[allow(dead_code)]
+    pub fn ranges(&self) -> &[RangeInclusive<T>] {
+        &self.ranges
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Adds every number in `range`, an empty range adds nothing.
+    #[allow(dead_code)]
+    pub fn insert(&mut self, range: RangeInclusive<T>) {
+        let mut ranges = std::mem::take(&mut self.ranges);
+        ranges.push(range);
+        *self = ranges.into_iter().collect();
+    }
+
+    /// Whether `value` is in the set, by binary search.
+    pub fn contains(&self, value: &T) -> bool {
+        let after = self.ranges.partition_point(|r| r.start() <= value);
+        after > 0 && self.ranges[after - 1].end() >= value
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.ranges
+            .iter()
+            .chain(other.ranges.iter())
+            .cloned()
+            .collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (mut i, mut j) = (0, 0);
+        let mut ranges = vec![];
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = *a.start().max(b.start());
+            let end = *a.end().min(b.end());
+            if start <= end {
+                ranges.push(start..=end);
+            }
+            // move past whichever finishes first, it can't overlap anything else
+            if a.end() < b.end() {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// The numbers in this set that aren't in `other`.
+    #[allow(dead_code)]
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let mut j = 0;
+        for range in &self.ranges {
+            let (mut start, end) = (*range.start(), *range.end());
+            // `other`'s ranges entirely before this one can't remove anything from later ones
+            while j < other.ranges.len() && *other.ranges[j].end() < start {
+                j += 1;
+            }
+            let mut k = j;
+            let mut remaining = true;
+            while remaining && k < other.ranges.len() && *other.ranges[k].start() <= end {
+                let removed = &other.ranges[k];
+                if *removed.start() > start {
+                    ranges.push(start..=*removed.start() - T::one());
+                }
+                match removed.end().checked_add(&T::one()) {
+                    Some(next) if next <= end => start = next,
+                    _ => remaining = false,
+                }
+                k += 1;
+            }
+            if remaining {
+                ranges.push(start..=end);
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// How many numbers are in the set, `None` if that's more than `u128::MAX`,
+    /// which only happens for sets of nearly every `u128` or `i128`.
+    #[allow(dead_code)]
+    pub fn total_length(&self) -> Option<u128> {
+        self.ranges.iter().try_fold(0u128, |total, r| {
+            // the range is never reversed, so this can't underflow
+            let span = Self::offset(*r.end()) - Self::offset(*r.start());
+            total.checked_add(span)?.checked_add(1)
+        })
+    }
+
+    /// `n` mapped onto `u128` keeping the order, so differences always fit.
+    fn offset(n: T) -> u128 {
+        match n.to_u128() {
+            Some(unsigned) if T::min_value() == T::zero() => unsigned,
+            // flipping the sign bit moves i128::MIN to 0 and i128::MAX to u128::MAX
+            _ => (n.to_i128().expect("signed, so fits in an i128") as u128) ^ (1 << 127),
+        }
+    }
+
+    /// The ranges missing between the lowest and highest numbers in the set.
+    #[allow(dead_code)]
+    pub fn gaps(&self) -> impl Iterator<Item = RangeInclusive<T>> + '_ {
+        self.ranges
+            .windows(2)
+            .map(|pair| *pair[0].end() + T::one()..=*pair[1].start() - T::one())
+    }
+}
+
+impl<T: PrimInt> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        let mut sorted: Vec<_> = iter.into_iter().filter(|r| !r.is_empty()).collect();
+        sorted.sort_by_key(|r| *r.start());
+        let mut ranges: Vec<RangeInclusive<T>> = vec![];
+        for range in sorted {
+            match ranges.last_mut() {
+                // overlapping or touching, if `end + 1` overflows it covers the rest
+                Some(last)
+                    if last
+                        .end()
+                        .checked_add(&T::one())
+                        .is_none_or(|after| after >= *range.start()) =>
+                {
+                    if range.end() > last.end() {
+                        *last = *last.start()..=*range.end();
+                    }
+                }
+                _ => ranges.push(range),
+            }
+        }
+        IntervalSet { ranges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[RangeInclusive<i32>]) -> IntervalSet<i32> {
+        ranges.iter().cloned().collect()
+    }
+
+    #[test]
+    fn merging() {
+        let s = set(&[
+            5..=7,
+            1..=3,
+            4..=4,
+            10..=12,
+            11..=11,
+            RangeInclusive::new(20, 19),
+        ]);
+        assert_eq!(s.ranges(), &[1..=7, 10..=12]);
+        assert_eq!(s.total_length(), Some(10));
+        assert_eq!(s.gaps().collect::<Vec<_>>(), vec![8..=9]);
+        let mut s = s;
+        s.insert(8..=9);
+        assert_eq!(s.ranges(), &[1..=12]);
+        assert_eq!(s.gaps().count(), 0);
+        let full: IntervalSet<u8> = vec![0..=100, 101..=255].into_iter().collect();
+        assert_eq!(full.ranges(), &[0..=255]);
+        assert_eq!(full.total_length(), Some(256));
+    }
+
+    #[test]
+    fn lengths_of_wide_types() {
+        let everything: IntervalSet<i8> = vec![i8::MIN..=i8::MAX].into_iter().collect();
+        assert_eq!(everything.total_length(), Some(256));
+        let huge: IntervalSet<u128> = vec![0..=5, (1 << 127)..=u128::MAX - 1]
+            .into_iter()
+            .collect();
+        assert_eq!(huge.total_length(), Some(u128::MAX - (1 << 127) + 6));
+        let signed: IntervalSet<i128> = vec![i128::MIN..=-1].into_iter().collect();
+        assert_eq!(signed.total_length(), Some(1 << 127));
+        let all: IntervalSet<u128> = vec![0..=u128::MAX].into_iter().collect();
+        assert_eq!(all.total_length(), None);
+    }
+
+    #[test]
+    fn lookups() {
+        let s = set(&[-5..=-1, 3..=3, 10..=20]);
+        for n in -10..30 {
+            let expected = (-5..=-1).contains(&n) || n == 3 || (10..=20).contains(&n);
+            assert_eq!(s.contains(&n), expected, "{}", n);
+        }
+        assert!(!IntervalSet::new().contains(&0));
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = set(&[1..=10, 20..=30]);
+        let b = set(&[5..=25, 28..=28, 40..=50]);
+        assert_eq!(a.union(&b).ranges(), &[1..=30, 40..=50]);
+        assert_eq!(a.intersection(&b).ranges(), &[5..=10, 20..=25, 28..=28]);
+        assert_eq!(a.difference(&b).ranges(), &[1..=4, 26..=27, 29..=30]);
+        assert_eq!(b.difference(&a).ranges(), &[11..=19, 40..=50]);
+        assert!(a.difference(&a).is_empty());
+        assert_eq!(a.difference(&IntervalSet::new()), a);
+        let top: IntervalSet<u8> = vec![200..=255].into_iter().collect();
+        let hole: IntervalSet<u8> = vec![210..=220, 250..=255].into_iter().collect();
+        assert_eq!(top.difference(&hole).ranges(), &[200..=209, 221..=249]);
+    }
+}
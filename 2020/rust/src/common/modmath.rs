@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+
+/// Unsigned integers the functions here work with. The arithmetic is all
+/// done in `u128`, which every result (being less than the modulus) fits back into.
+///
+/// Moduli have to be below `2^127`, so that Bézout coefficients fit in an `i128`.
+pub trait ModInt: Copy + Debug + Into<u128> + TryFrom<u128> {}
+
+impl ModInt for u32 {}
+impl ModInt for u64 {}
+impl ModInt for u128 {}
+
+fn narrow<T: ModInt>(n: u128) -> T {
+    T::try_from(n)
+        .ok()
+        .expect("results are smaller than the modulus, so fit")
+}
+
+/// The greatest common divisor `g` of `a` and `b`, along with `x` and `y`
+/// such that `a * x + b * y == g`.
+pub fn egcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// `a * b mod m`, without overflowing even when the product doesn't fit in a `u128`.
+fn mul_mod_u128(a: u128, b: u128, m: u128) -> u128 {
+    let (mut a, mut b) = (a % m, b % m);
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+    // double and add, a is below m < 2^127 so doubling can't overflow
+    let mut result = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = (result + a) % m;
+        }
+        a = (a << 1) % m;
+        b >>= 1;
+    }
+    result
+}
+
+#[allow(dead_code)]
+pub fn mul_mod<T: ModInt>(a: T, b: T, m: T) -> T {
+    narrow(mul_mod_u128(a.into(), b.into(), m.into()))
+}
+
+fn pow_mod_u128(base: u128, mut exp: u128, m: u128) -> u128 {
+    let mut base = base % m;
+    let mut result = 1 % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod_u128(result, base, m);
+        }
+        base = mul_mod_u128(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// `base^exp mod m`, by repeated squaring.
+pub fn pow_mod<T: ModInt>(base: T, exp: T, m: T) -> T {
+    narrow(pow_mod_u128(base.into(), exp.into(), m.into()))
+}
+
+fn inverse_u128(a: u128, m: u128) -> Option<u128> {
+    let (g, x, _) = egcd((a % m) as i128, m as i128);
+    if g != 1 {
+        return None;
+    }
+    Some(x.rem_euclid(m as i128) as u128)
+}
+
+/// The `x` where `a * x ≡ 1 (mod m)`, if `a` and `m` are coprime.
+#[allow(dead_code)]
+pub fn inverse<T: ModInt>(a: T, m: T) -> Option<T> {
+    inverse_u128(a.into(), m.into()).map(narrow)
+}
+
+/// All the numbers `x ≡ residue (mod modulus)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Congruence<T> {
+    pub residue: T,
+    pub modulus: T,
+}
+
+#[allow(dead_code)]
+impl<T: ModInt> Congruence<T> {
+    /// The residue is reduced, so it's always below the modulus.
+    pub fn new(residue: T, modulus: T) -> Self {
+        Congruence {
+            residue: narrow(residue.into() % modulus.into()),
+            modulus,
+        }
+    }
+
+    /// The smallest number at or above `n` that satisfies the congruence,
+    /// `None` if that doesn't fit in a `T`.
+    pub fn next_at_or_after(&self, n: T) -> Option<T> {
+        let (n, r, m) = (n.into(), self.residue.into(), self.modulus.into());
+        let wait = (r + m - n % m) % m;
+        T::try_from(n.checked_add(wait)?).ok()
+    }
+
+    /// The numbers satisfying both congruences, which don't need coprime moduli.
+    /// `None` if there aren't any, or the combined modulus doesn't fit in a `T`.
+    pub fn merge(&self, other: &Self) -> Option<Self> {
+        let (r1, m1) = (self.residue.into(), self.modulus.into());
+        let (r2, m2) = (other.residue.into(), other.modulus.into());
+        let (g, p, _) = egcd(m1 as i128, m2 as i128);
+        let g = g as u128;
+        let diff = (r2 + m2 - r1 % m2) % m2;
+        if diff % g != 0 {
+            return None;
+        }
+        // x = r1 + m1 * k, where m1 * k ≡ diff (mod m2)
+        let reduced = m2 / g;
+        let p = p.rem_euclid(reduced as i128) as u128;
+        let k = mul_mod_u128(diff / g, p, reduced);
+        let modulus = m1.checked_mul(reduced)?;
+        let residue = (r1 + mul_mod_u128(m1, k, modulus)) % modulus;
+        Some(Congruence {
+            residue: T::try_from(residue).ok()?,
+            modulus: T::try_from(modulus).ok()?,
+        })
+    }
+}
+
+/// The Chinese remainder theorem, the congruence satisfied by exactly the numbers
+/// that satisfy every one of `congruences`. `None` if they contradict each other.
+pub fn crt<T, I>(congruences: I) -> Option<Congruence<T>>
+where
+    T: ModInt,
+    I: IntoIterator<Item = Congruence<T>>,
+{
+    let one: T = narrow(1);
+    congruences
+        .into_iter()
+        .try_fold(Congruence::new(narrow(0), one), |acc, c| acc.merge(&c))
+}
+
+/// The smallest `x` where `base^x ≡ target (mod m)`, found with baby-step giant-step
+/// in `O(sqrt(m))` time and space. `base` has to be coprime to `m`.
+pub fn discrete_log<T: ModInt>(base: T, target: T, m: T) -> Option<T> {
+    let (base, target, m) = (base.into(), target.into() % m.into(), m.into());
+    let steps = (m as f64).sqrt().ceil() as u128 + 1;
+    // baby steps, base^j for every j below steps, keeping the smallest j
+    let mut baby = HashMap::new();
+    let mut value = 1 % m;
+    for j in 0..steps {
+        baby.entry(value).or_insert(j);
+        value = mul_mod_u128(value, base, m);
+    }
+    // giant steps, target * base^(-steps * i) is a baby step when x = steps * i + j
+    let giant = pow_mod_u128(inverse_u128(base, m)?, steps, m);
+    let mut value = target;
+    for i in 0..steps {
+        if let Some(j) = baby.get(&value) {
+            return Some(narrow(i * steps + j));
+        }
+        value = mul_mod_u128(value, giant, m);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_and_inverse() {
+        let (g, x, y) = egcd(240, 46);
+        assert_eq!(g, 2);
+        assert_eq!(240 * x + 46 * y, 2);
+        assert_eq!(inverse(3u64, 11), Some(4));
+        assert_eq!(inverse(4u64, 10), None);
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(pow_mod(4u64, 13, 497), 445);
+        assert_eq!(pow_mod(5u64, 0, 1), 0);
+        // the intermediate products don't fit in a u128
+        let big = (1u128 << 126) + 1;
+        assert_eq!(mul_mod(big - 1, big - 1, big), 1);
+        assert_eq!(pow_mod(2u128, 127, big), big - 2);
+    }
+
+    #[test]
+    fn remainders() {
+        let c = |r, m| Congruence::<u64>::new(r, m);
+        assert_eq!(crt(vec![c(2, 3), c(3, 5), c(2, 7)]), Some(c(23, 105)));
+        // moduli sharing a factor
+        assert_eq!(crt(vec![c(3, 4), c(5, 6)]), Some(c(11, 12)));
+        assert_eq!(crt(vec![c(1, 4), c(2, 6)]), None);
+        assert_eq!(crt(vec![]), Some(c(0, 1)));
+        assert_eq!(c(3, 7).next_at_or_after(11), Some(17));
+        assert_eq!(c(3, 7).next_at_or_after(10), Some(10));
+        // u64::MAX is 1 mod 7, so the next 0 mod 7 is past it
+        assert_eq!(c(0, 7).next_at_or_after(u64::MAX), None);
+        assert_eq!(
+            Congruence::<u128>::new(0, 7).next_at_or_after(u128::MAX),
+            None
+        );
+    }
+
+    #[test]
+    fn logarithms() {
+        assert_eq!(discrete_log(7u64, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(3u64, 13, 17), Some(4));
+        assert_eq!(discrete_log(3u64, 1, 17), Some(0));
+        // 2 only generates 1, 2 and 4 mod 7
+        assert_eq!(discrete_log(2u64, 3, 7), None);
+    }
+}
@@ -1,37 +1,179 @@
+use regex::Match;
 use std::{
     char::ParseCharError,
     error::Error,
     fmt::{self, Display, Formatter},
     num::ParseIntError,
+    ops::Range,
+    str::FromStr,
+    sync::Arc,
 };
 
-#[derive(Debug, Clone)]
-pub struct ParseError;
+/// Describes where and why some input could not be parsed.
+///
+/// Every piece of context is optional, parsers fill in what they know and
+/// callers further up (that know which line they're on, for example) can add
+/// the rest with the `at_*`/`with_*` builder methods.
+#[derive(Debug, Clone, Default)]
+pub struct ParseError {
+    /// 1-indexed line of the input.
+    line: Option<usize>,
+    /// 0-indexed, half-open span of characters within the line.
+    columns: Option<Range<usize>>,
+    /// The text that could not be parsed.
+    text: Option<String>,
+    /// Human readable description of what the input should have looked like.
+    expected: Option<String>,
+    source: Option<Arc<dyn Error + Send + Sync>>,
+}
+
+#[allow(dead_code)]
+impl ParseError {
+    /// An error with no context at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An error for input that didn't match the given shape, such as `"acc +1"`.
+    pub fn expected(shape: impl Into<String>) -> Self {
+        Self::new().with_expected(shape)
+    }
+
+    /// Sets the (1-indexed) input line number.
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    /// Sets the span of (0-indexed) character columns within the line.
+    ///
+    /// If a span is already present, it's assumed to be relative to the start
+    /// of `columns`, so nested parsers can each report their own offset.
+    pub fn at_columns(mut self, columns: Range<usize>) -> Self {
+        self.columns = Some(match self.columns {
+            Some(inner) => columns.start + inner.start..columns.start + inner.end,
+            None => columns,
+        });
+        self
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn with_expected(mut self, shape: impl Into<String>) -> Self {
+        self.expected = Some(shape.into());
+        self
+    }
+
+    pub fn with_source(mut self, source: impl Error + Send + Sync + 'static) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn columns(&self) -> Option<Range<usize>> {
+        self.columns.clone()
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn expected_shape(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+}
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "Unable to parse input.")
+        write!(f, "Unable to parse input")?;
+        if let Some(line) = self.line {
+            write!(f, " at line {}", line)?;
+        }
+        if let Some(columns) = &self.columns {
+            // displayed 1-indexed and inclusive, like an editor would
+            if columns.end <= columns.start + 1 {
+                write!(f, ", column {}", columns.start + 1)?;
+            } else {
+                write!(f, ", columns {}-{}", columns.start + 1, columns.end)?;
+            }
+        }
+        if let Some(text) = &self.text {
+            write!(f, ": {:?}", text)?;
+        }
+        if let Some(expected) = &self.expected {
+            write!(f, " (expected {})", expected)?;
+        }
+        Ok(())
     }
 }
 
 impl Error for ParseError {
-    fn description(&self) -> &str {
-        "Unable to parse input."
-    }
-
-    fn cause(&self) -> Option<&dyn Error> {
-        None
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|e| e.as_ref() as &(dyn Error + 'static))
     }
 }
 
 impl From<ParseIntError> for ParseError {
-    fn from(_error: ParseIntError) -> Self {
-        ParseError
+    fn from(error: ParseIntError) -> Self {
+        ParseError::expected("an integer").with_source(error)
     }
 }
 
 impl From<ParseCharError> for ParseError {
-    fn from(_error: ParseCharError) -> Self {
-        ParseError
+    fn from(error: ParseCharError) -> Self {
+        ParseError::expected("a single character").with_source(error)
+    }
+}
+
+/// Parses the text of a regex match.
+/// On failure, the error notes the text and where in the line it came from
+/// (regex offsets are in bytes, which are the same as columns for ASCII input).
+pub fn parse_match<T>(m: Match) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: Into<ParseError>,
+{
+    m.as_str().parse().map_err(|e: T::Err| {
+        e.into()
+            .with_text(m.as_str())
+            .at_columns(m.start()..m.end())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_with_location() {
+        let err = ParseError::expected("\"acc +1\"")
+            .with_text("acc 1")
+            .at_columns(4..5)
+            .at_line(3);
+        assert_eq!(
+            err.to_string(),
+            "Unable to parse input at line 3, column 5: \"acc 1\" (expected \"acc +1\")"
+        );
+    }
+
+    #[test]
+    fn nested_columns_are_offset() {
+        let err = ParseError::new().at_columns(1..3).at_columns(10..20);
+        assert_eq!(err.columns(), Some(11..13));
+    }
+
+    #[test]
+    fn keeps_source() {
+        let err = ParseError::from("x".parse::<u32>().unwrap_err());
+        assert!(err.source().is_some());
+        assert_eq!(err.expected_shape(), Some("an integer"));
     }
 }
@@ -0,0 +1,95 @@
+use crate::common::parse_error::ParseError;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How generators should treat input lines that can't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Any malformed line is an error (the default).
+    Strict,
+    /// Malformed lines are skipped, the rest of the input is still used.
+    Lenient,
+}
+
+/// The generators are called by the runner with only the input text,
+/// so the mode is a process-wide setting rather than a parameter.
+static LENIENT: AtomicBool = AtomicBool::new(false);
+
+#[allow(dead_code)]
+impl ParseMode {
+    /// The mode that generators currently use.
+    pub fn current() -> Self {
+        if LENIENT.load(Ordering::Relaxed) {
+            ParseMode::Lenient
+        } else {
+            ParseMode::Strict
+        }
+    }
+
+    /// Change the mode used by all generators from now on.
+    pub fn set_current(mode: ParseMode) {
+        LENIENT.store(mode == ParseMode::Lenient, Ordering::Relaxed);
+    }
+
+    /// Collects parsed items, each paired with the (1-indexed) line it came from.
+    ///
+    /// In strict mode the first error is returned, tagged with its line number.
+    /// In lenient mode any items that failed to parse are dropped.
+    pub fn collect<T, I>(self, items: I) -> Result<Vec<T>, ParseError>
+    where
+        I: IntoIterator<Item = (usize, Result<T, ParseError>)>,
+    {
+        let mut result = vec![];
+        for (line, item) in items {
+            match item {
+                Ok(item) => result.push(item),
+                Err(e) if self == ParseMode::Strict => return Err(e.at_line(line)),
+                Err(_) => continue,
+            }
+        }
+        Ok(result)
+    }
+
+    /// Parses every line of the input with `parse`.
+    pub fn parse_lines_with<T, F>(self, input: &str, mut parse: F) -> Result<Vec<T>, ParseError>
+    where
+        F: FnMut(&str) -> Result<T, ParseError>,
+    {
+        self.collect(
+            input
+                .lines()
+                .enumerate()
+                .map(|(i, line)| (i + 1, parse(line))),
+        )
+    }
+}
+
+/// Parses every line of the input as a `T`, using the current `ParseMode`.
+pub fn parse_lines<T>(input: &str) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr,
+    T::Err: Into<ParseError>,
+{
+    ParseMode::current().parse_lines_with(input, |line| line.parse().map_err(Into::into))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_numbers(mode: ParseMode, input: &str) -> Result<Vec<u32>, ParseError> {
+        mode.parse_lines_with(input, |l| l.parse().map_err(ParseError::from))
+    }
+
+    #[test]
+    fn strict_rejects_malformed_line() {
+        let err = parse_numbers(ParseMode::Strict, "1\n2\nthree\n4").unwrap_err();
+        assert_eq!(err.line(), Some(3));
+    }
+
+    #[test]
+    fn lenient_skips_malformed_line() {
+        let nums = parse_numbers(ParseMode::Lenient, "1\n2\nthree\n4").unwrap();
+        assert_eq!(nums, vec![1, 2, 4]);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::common::modmath::{crt, Congruence};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::ops::Range;
+
+/// When a bus leaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Departure {
+    pub time: u64,
+    pub bus: u64,
+}
+
+/// The first time at or after `after` that `bus` leaves.
+/// Every bus leaves at time 0, and then every `bus` minutes.
+/// `None` if the bus is 0, which never leaves, or the time doesn't fit in a `u64`.
+pub fn next_departure(bus: u64, after: u64) -> Option<u64> {
+    if bus == 0 {
+        return None;
+    }
+    Congruence::new(0, bus).next_at_or_after(after)
+}
+
+/// The earliest time `t` where each `(bus, offset)` leaves at `t + offset`.
+/// `None` if those buses never line up like that, or any bus is 0.
+pub fn earliest_alignment<I>(schedule: I) -> Option<u64>
+where
+    I: IntoIterator<Item = (u64, u64)>,
+{
+    // t + offset ≡ 0, so t ≡ -offset (mod bus)
+    let congruences: Option<Vec<_>> = schedule
+        .into_iter()
+        .map(|(bus, offset)| match bus {
+            0 => None,
+            _ => Some(Congruence::new(bus - offset % bus, bus)),
+        })
+        .collect();
+    crt(congruences?).map(|c| c.residue)
+}
+
+/// A list of buses, each leaving every `bus` minutes from time 0.
+/// Gaps in the list (`None`) only matter for the offsets in alignments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    buses: Vec<Option<u64>>,
+}
+
+impl Schedule {
+    /// `None` if any bus is 0, which would never leave.
+    pub fn new(buses: Vec<Option<u64>>) -> Option<Self> {
+        if buses.contains(&Some(0)) {
+            return None;
+        }
+        Some(Schedule { buses })
+    }
+
+    /// The buses, without the gaps.
+    pub fn buses(&self) -> impl Iterator<Item = u64> + '_ {
+        self.buses.iter().flatten().copied()
+    }
+
+    /// The first bus to leave at or after `after`.
+    pub fn first_departure(&self, after: u64) -> Option<Departure> {
+        self.buses()
+            .filter_map(|bus| {
+                let time = next_departure(bus, after)?;
+                Some(Departure { time, bus })
+            })
+            .min()
+    }
+
+    /// Every departure of every bus within `window`, ordered by time and then bus.
+    pub fn departures(&self, window: Range<u64>) -> Departures {
+        let upcoming = self
+            .buses()
+            .filter_map(|bus| {
+                let time = next_departure(bus, window.start)?;
+                Some(Departure { time, bus })
+            })
+            .filter(|d| d.time < window.end)
+            .map(Reverse)
+            .collect();
+        Departures {
+            upcoming,
+            end: window.end,
+        }
+    }
+
+    /// The earliest time where each bus leaves as many minutes after it as it
+    /// is positions after the start of the list.
+    pub fn earliest_alignment(&self) -> Option<u64> {
+        earliest_alignment(self.offsets())
+    }
+
+    /// Like `earliest_alignment`, but only for the buses in `subset`.
+    /// Buses that aren't in the schedule are ignored.
+    pub fn earliest_alignment_of(&self, subset: &[u64]) -> Option<u64> {
+        earliest_alignment(self.offsets().filter(|(bus, _)| subset.contains(bus)))
+    }
+
+    /// Each bus, and its position in the list.
+    fn offsets(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.buses
+            .iter()
+            .enumerate()
+            .filter_map(|(offset, bus)| Some(((*bus)?, offset as u64)))
+    }
+}
+
+/// Every departure in a window of time, in order, see `Schedule::departures`.
+#[derive(Debug, Clone)]
+pub struct Departures {
+    /// The next departure of each bus.
+    upcoming: BinaryHeap<Reverse<Departure>>,
+    end: u64,
+}
+
+impl Iterator for Departures {
+    type Item = Departure;
+
+    fn next(&mut self) -> Option<Departure> {
+        let Reverse(departure) = self.upcoming.pop()?;
+        if departure.time >= self.end {
+            return None;
+        }
+        match departure.time.checked_add(departure.bus) {
+            Some(time) if time < self.end => {
+                self.upcoming.push(Reverse(Departure { time, ..departure }))
+            }
+            _ => {}
+        }
+        Some(departure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Schedule {
+        Schedule::new(vec![
+            Some(7),
+            Some(13),
+            None,
+            None,
+            Some(59),
+            None,
+            Some(31),
+            Some(19),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn next_departures() {
+        assert_eq!(next_departure(59, 939), Some(944));
+        assert_eq!(next_departure(7, 938), Some(938));
+        assert_eq!(next_departure(7, 0), Some(0));
+        assert_eq!(
+            example().first_departure(939),
+            Some(Departure { time: 944, bus: 59 })
+        );
+        assert_eq!(Schedule::new(vec![None]).unwrap().first_departure(1), None);
+        assert_eq!(Schedule::new(vec![Some(0)]), None);
+    }
+
+    #[test]
+    fn unreachable_departures() {
+        assert_eq!(next_departure(0, 10), None);
+        assert_eq!(earliest_alignment(vec![(7, 0), (0, 1)]), None);
+        // the next departure would be after u64::MAX
+        assert_eq!(next_departure(7, u64::MAX), None);
+        let late = Schedule::new(vec![Some(7), Some(u64::MAX)]).unwrap();
+        assert_eq!(
+            late.first_departure(u64::MAX),
+            Some(Departure {
+                time: u64::MAX,
+                bus: u64::MAX
+            })
+        );
+        // 7 would next leave after u64::MAX, and the window ends before the other bus leaves
+        let times: Vec<_> = late
+            .departures(u64::MAX - 10..u64::MAX)
+            .map(|d| d.time)
+            .collect();
+        assert_eq!(times, vec![u64::MAX - 8, u64::MAX - 1]);
+    }
+
+    #[test]
+    fn departures_in_a_window() {
+        let times: Vec<_> = example()
+            .departures(938..950)
+            .map(|d| (d.time, d.bus))
+            .collect();
+        assert_eq!(times, vec![(938, 7), (944, 59), (945, 7), (949, 13)]);
+        assert_eq!(example().departures(10..10).count(), 0);
+    }
+
+    #[test]
+    fn alignments() {
+        assert_eq!(example().earliest_alignment(), Some(1068781));
+        // 7 then 13 a minute later, ignoring the rest
+        assert_eq!(example().earliest_alignment_of(&[7, 13]), Some(77));
+        assert_eq!(earliest_alignment(vec![(4, 0), (6, 1)]), None);
+    }
+}
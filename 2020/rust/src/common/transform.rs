@@ -0,0 +1,179 @@
+use crate::common::direction::Rotation;
+use crate::common::vector2::Vector2;
+use num::{Num, Signed};
+use std::ops::Mul;
+
+/// A line through the origin to mirror points across.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(dead_code)]
+pub enum Reflection {
+    /// (x, y) -> (x, -y)
+    XAxis,
+    /// (x, y) -> (-x, y)
+    YAxis,
+    /// the line y = x, (x, y) -> (y, x)
+    Diagonal,
+    /// the line y = -x, (x, y) -> (-y, -x)
+    AntiDiagonal,
+}
+
+/// An integer 2x2 matrix, used to rotate and reflect `Vector2`s.
+///
+/// ```text
+/// | xx xy |   | x |
+/// | yx yy | * | y |
+/// ```
+///
+/// Transforms compose with `then` (or `*`, where `a * b` applies `b` first).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Transform2 {
+    xx: i32,
+    xy: i32,
+    yx: i32,
+    yy: i32,
+}
+
+#[allow(dead_code)]
+impl Transform2 {
+    pub const IDENTITY: Transform2 = Transform2::new(1, 0, 0, 1);
+
+    /// The 8 orientations of a square (the dihedral group D4):
+    /// the 4 clockwise quarter turns, then the same turns applied after a flip across the y axis.
+    pub const ALL: [Transform2; 8] = [
+        Transform2::new(1, 0, 0, 1),
+        Transform2::new(0, 1, -1, 0),
+        Transform2::new(-1, 0, 0, -1),
+        Transform2::new(0, -1, 1, 0),
+        Transform2::new(-1, 0, 0, 1),
+        Transform2::new(0, 1, 1, 0),
+        Transform2::new(1, 0, 0, -1),
+        Transform2::new(0, -1, -1, 0),
+    ];
+
+    /// Rows of the matrix, top to bottom.
+    pub const fn new(xx: i32, xy: i32, yx: i32, yy: i32) -> Self {
+        Transform2 { xx, xy, yx, yy }
+    }
+
+    /// A rotation about the origin, `None` unless it's a multiple of 90°.
+    pub fn rotation(rotation: Rotation) -> Option<Self> {
+        let turns = rotation.quarter_turns()? as usize;
+        Some(Self::ALL[turns])
+    }
+
+    pub fn reflection(reflection: Reflection) -> Self {
+        match reflection {
+            Reflection::XAxis => Transform2::new(1, 0, 0, -1),
+            Reflection::YAxis => Transform2::new(-1, 0, 0, 1),
+            Reflection::Diagonal => Transform2::new(0, 1, 1, 0),
+            Reflection::AntiDiagonal => Transform2::new(0, -1, -1, 0),
+        }
+    }
+
+    /// This transform followed by `other`.
+    pub fn then(&self, other: Transform2) -> Self {
+        other * *self
+    }
+
+    pub fn determinant(&self) -> i32 {
+        self.xx * self.yy - self.xy * self.yx
+    }
+
+    /// Does this transform mirror points (rather than only rotating them)?
+    pub fn is_reflection(&self) -> bool {
+        self.determinant() < 0
+    }
+
+    /// The transform that undoes this one, only exists when the determinant is ±1.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() != 1 {
+            return None;
+        }
+        Some(Transform2::new(
+            self.yy * det,
+            -self.xy * det,
+            -self.yx * det,
+            self.xx * det,
+        ))
+    }
+
+    /// Transforms the vector about the origin.
+    pub fn apply<N>(&self, v: Vector2<N>) -> Vector2<N>
+    where
+        N: Num + Ord + Copy + Signed + From<i32>,
+    {
+        Vector2::new(
+            N::from(self.xx) * v.x + N::from(self.xy) * v.y,
+            N::from(self.yx) * v.x + N::from(self.yy) * v.y,
+        )
+    }
+
+    /// Transforms the vector about `pivot` instead of the origin.
+    pub fn apply_about<N>(&self, v: Vector2<N>, pivot: Vector2<N>) -> Vector2<N>
+    where
+        N: Num + Ord + Copy + Signed + From<i32>,
+    {
+        self.apply(v - pivot) + pivot
+    }
+}
+
+impl Default for Transform2 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Transform2 {
+    type Output = Self;
+    /// Matrix product, `self * other` applies `other` first.
+    fn mul(self, other: Self) -> Self {
+        Transform2::new(
+            self.xx * other.xx + self.xy * other.yx,
+            self.xx * other.xy + self.xy * other.yy,
+            self.yx * other.xx + self.yy * other.yx,
+            self.yx * other.xy + self.yy * other.yy,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn rotations_match_quarter_turns() {
+        let v = Vector2::new(10, 4);
+        let right = Transform2::rotation(Rotation::clockwise(90)).unwrap();
+        let left = Transform2::rotation(Rotation::anticlockwise(90)).unwrap();
+        assert_eq!(right.apply(v), v.rotate_right_about_origin());
+        assert_eq!(left.apply(v), v.rotate_left_about_origin());
+        assert_eq!(Transform2::rotation(Rotation::clockwise(45)), None);
+    }
+
+    #[test]
+    fn dihedral_group_is_closed() {
+        let all: HashSet<_> = Transform2::ALL.iter().copied().collect();
+        assert_eq!(all.len(), 8);
+        for a in Transform2::ALL.iter() {
+            assert!(a.inverse().is_some_and(|i| all.contains(&i)));
+            for b in Transform2::ALL.iter() {
+                assert!(all.contains(&a.then(*b)));
+            }
+        }
+        assert_eq!(all.iter().filter(|t| t.is_reflection()).count(), 4);
+    }
+
+    #[test]
+    fn reflect_about_pivot() {
+        let diagonal = Transform2::reflection(Reflection::Diagonal);
+        let pivot = Vector2::new(1, 1);
+        assert_eq!(
+            diagonal.apply_about(Vector2::new(3, 1), pivot),
+            Vector2::new(1, 3)
+        );
+        let flip = Transform2::reflection(Reflection::YAxis);
+        assert_eq!(flip.then(flip), Transform2::IDENTITY);
+    }
+}
@@ -1,7 +1,13 @@
+use crate::common::direction::Rotation;
+use crate::common::transform::{Reflection, Transform2};
+use num::traits::Euclid;
 use num::{Num, Signed};
 use std::cmp::{max, min};
+use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
-use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, RangeInclusive, Sub, SubAssign,
+};
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Vector2<N: Num + Ord + Copy> {
@@ -83,6 +89,57 @@ where
     pub fn man_distance_to_origin(&self) -> N {
         self.man_distance_to(&Self::zero())
     }
+
+    /// the chebyshev (chessboard) distance between these 2 points
+    pub fn chebyshev_distance_to(&self, other: &Self) -> N {
+        let x_dist = max(self.x, other.x) - min(self.x, other.x);
+        let y_dist = max(self.y, other.y) - min(self.y, other.y);
+        max(x_dist, y_dist)
+    }
+
+    /// Multiply each component by the matching component of `other`.
+    pub fn component_mul(&self, other: &Self) -> Self {
+        Self {
+            x: self.x * other.x,
+            y: self.y * other.y,
+        }
+    }
+
+    /// Clamp each component to lie within the matching components of `bounds`.
+    pub fn clamp_to(&self, bounds: RangeInclusive<Self>) -> Self {
+        let (low, high) = bounds.into_inner();
+        Self {
+            x: self.x.clamp(low.x, high.x),
+            y: self.y.clamp(low.y, high.y),
+        }
+    }
+
+    /// Convert each component to another numeric type,
+    /// failing if either doesn't fit (e.g. a negative `i32` into a `usize`).
+    pub fn try_cast<M>(&self) -> Result<Vector2<M>, M::Error>
+    where
+        M: Num + Ord + Copy + TryFrom<N>,
+    {
+        Ok(Vector2 {
+            x: M::try_from(self.x)?,
+            y: M::try_from(self.y)?,
+        })
+    }
+}
+
+#[allow(dead_code)]
+impl<N> Vector2<N>
+where
+    N: Num + Ord + Copy + Euclid,
+{
+    /// Wrap each component into `0..bounds`, like the x coordinate of a repeating map.
+    /// Negative components wrap around from the far side.
+    pub fn rem_euclid(&self, bounds: &Self) -> Self {
+        Self {
+            x: self.x.rem_euclid(&bounds.x),
+            y: self.y.rem_euclid(&bounds.y),
+        }
+    }
 }
 
 impl<N> Display for Vector2<N>
@@ -94,6 +151,7 @@ where
     }
 }
 
+#[allow(dead_code)]
 impl<N> Vector2<N>
 where
     N: Num + Ord + Copy + Neg + Signed,
@@ -110,6 +168,43 @@ where
             y: -self.x,
         }
     }
+
+    /// the sign of each component: -1, 0 or 1
+    pub fn signum(&self) -> Self {
+        Self {
+            x: self.x.signum(),
+            y: self.y.signum(),
+        }
+    }
+
+    /// the absolute value of each component
+    pub fn abs(&self) -> Self {
+        Self {
+            x: self.x.abs(),
+            y: self.y.abs(),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl<N> Vector2<N>
+where
+    N: Num + Ord + Copy + Signed + From<i32>,
+{
+    /// Rotates about the origin, `None` unless it's a multiple of 90°.
+    pub fn rotated(&self, rotation: Rotation) -> Option<Self> {
+        Transform2::rotation(rotation).map(|t| t.apply(*self))
+    }
+
+    /// Rotates about `pivot`, `None` unless it's a multiple of 90°.
+    pub fn rotated_about(&self, pivot: Self, rotation: Rotation) -> Option<Self> {
+        Transform2::rotation(rotation).map(|t| t.apply_about(*self, pivot))
+    }
+
+    /// Mirrors across a line through the origin.
+    pub fn reflected(&self, reflection: Reflection) -> Self {
+        Transform2::reflection(reflection).apply(*self)
+    }
 }
 
 impl<N> Neg for Vector2<N>
@@ -174,3 +269,98 @@ where
         };
     }
 }
+
+impl<N> Mul<N> for Vector2<N>
+where
+    N: Num + Ord + Copy,
+{
+    type Output = Self;
+    fn mul(self, amount: N) -> Self {
+        self.scaled(amount)
+    }
+}
+
+impl<N> MulAssign<N> for Vector2<N>
+where
+    N: Num + Ord + Copy,
+{
+    fn mul_assign(&mut self, amount: N) {
+        *self = self.scaled(amount);
+    }
+}
+
+impl<N> Div<N> for Vector2<N>
+where
+    N: Num + Ord + Copy,
+{
+    type Output = Self;
+    fn div(self, amount: N) -> Self {
+        Self {
+            x: self.x / amount,
+            y: self.y / amount,
+        }
+    }
+}
+
+impl<N> DivAssign<N> for Vector2<N>
+where
+    N: Num + Ord + Copy,
+{
+    fn div_assign(&mut self, amount: N) {
+        *self = *self / amount;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalar_operators() {
+        let v = Vector2::new(6, -4);
+        assert_eq!(v * 2, Vector2::new(12, -8));
+        assert_eq!(v / 2, Vector2::new(3, -2));
+        assert_eq!(v.component_mul(&Vector2::new(2, 3)), Vector2::new(12, -12));
+        assert_eq!(v.signum(), Vector2::new(1, -1));
+        assert_eq!(v.abs(), Vector2::new(6, 4));
+    }
+
+    #[test]
+    fn wrapping_and_clamping() {
+        let bounds = Vector2::new(5, 3);
+        assert_eq!(Vector2::new(7, -1).rem_euclid(&bounds), Vector2::new(2, 2));
+        assert_eq!(
+            Vector2::new(7, -1).clamp_to(Vector2::zero()..=bounds),
+            Vector2::new(5, 0)
+        );
+    }
+
+    #[test]
+    fn rotate_about_pivot() {
+        let v = Vector2::new(3, 1);
+        let pivot = Vector2::new(1, 1);
+        let rotated = |deg| v.rotated_about(pivot, Rotation::clockwise(deg));
+        assert_eq!(rotated(90), Some(Vector2::new(1, -1)));
+        assert_eq!(rotated(180), Some(Vector2::new(-1, 1)));
+        assert_eq!(rotated(-90), Some(Vector2::new(1, 3)));
+        assert_eq!(rotated(30), None);
+        assert_eq!(v.reflected(Reflection::AntiDiagonal), Vector2::new(-1, -3));
+    }
+
+    #[test]
+    fn distances() {
+        let a = Vector2::new(1, 2);
+        let b = Vector2::new(4, -5);
+        assert_eq!(a.man_distance_to(&b), 10);
+        assert_eq!(a.chebyshev_distance_to(&b), 7);
+    }
+
+    #[test]
+    fn casting() {
+        assert_eq!(
+            Vector2::new(3usize, 4).try_cast::<i32>(),
+            Ok(Vector2::new(3, 4))
+        );
+        assert!(Vector2::new(-1i32, 4).try_cast::<usize>().is_err());
+    }
+}
@@ -0,0 +1,313 @@
+use crate::common::vector2::Vector2;
+use num::{Num, Signed};
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Index, IndexMut, Neg, Sub, SubAssign};
+
+/// A point or vector in `N` dimensional space.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct VectorN<T: Num + Ord + Copy, const N: usize> {
+    pub coords: [T; N],
+}
+
+/// (x, y, z)
+#[allow(dead_code)]
+pub type Vector3<T> = VectorN<T, 3>;
+
+#[allow(dead_code)]
+impl<T, const N: usize> VectorN<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    pub fn new(coords: [T; N]) -> Self {
+        VectorN { coords }
+    }
+
+    /// The origin, all components are zero.
+    pub fn zero() -> Self {
+        VectorN {
+            coords: [T::zero(); N],
+        }
+    }
+
+    /// All components are one.
+    pub fn one() -> Self {
+        VectorN {
+            coords: [T::one(); N],
+        }
+    }
+
+    /// Extends a 2D vector into `N` dimensions, with zero for every other component.
+    pub fn from_vector2(v: Vector2<T>) -> Self {
+        assert!(N >= 2, "Dimension must be at least 2");
+        let mut coords = [T::zero(); N];
+        coords[0] = v.x;
+        coords[1] = v.y;
+        VectorN { coords }
+    }
+
+    /// The number of dimensions.
+    pub fn dimensions(&self) -> usize {
+        N
+    }
+
+    /// Apply scalar to this vector
+    pub fn scaled(&self, amount: T) -> Self {
+        self.map(|c| c * amount)
+    }
+
+    /// Apply `f` to every component.
+    pub fn map<F: Fn(T) -> T>(&self, f: F) -> Self {
+        let mut coords = self.coords;
+        for c in coords.iter_mut() {
+            *c = f(*c);
+        }
+        VectorN { coords }
+    }
+
+    /// Combine each component with the matching component of `other`.
+    fn zip_with<F: Fn(T, T) -> T>(&self, other: &Self, f: F) -> Self {
+        let mut coords = self.coords;
+        for (c, o) in coords.iter_mut().zip(other.coords.iter()) {
+            *c = f(*c, *o);
+        }
+        VectorN { coords }
+    }
+
+    /// the minimum of all components
+    pub fn min(&self) -> T {
+        self.coords.iter().copied().min().unwrap_or_else(T::zero)
+    }
+
+    /// the maximum of all components
+    pub fn max(&self) -> T {
+        self.coords.iter().copied().max().unwrap_or_else(T::zero)
+    }
+
+    /// summation of all components
+    pub fn sum(&self) -> T {
+        self.coords.iter().fold(T::zero(), |acc, c| acc + *c)
+    }
+
+    /// product of all components
+    pub fn product(&self) -> T {
+        self.coords.iter().fold(T::one(), |acc, c| acc * *c)
+    }
+
+    /// The absolute difference between each component.
+    fn distances_to<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = T> + 'a {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .map(|(a, b)| *a.max(b) - *a.min(b))
+    }
+
+    /// the manhattan distance between these 2 points
+    pub fn man_distance_to(&self, other: &Self) -> T {
+        self.distances_to(other).fold(T::zero(), |acc, d| acc + d)
+    }
+
+    /// the manhattan distance to the origin
+    pub fn man_distance_to_origin(&self) -> T {
+        self.man_distance_to(&Self::zero())
+    }
+
+    /// the chebyshev (chessboard) distance between these 2 points
+    pub fn chebyshev_distance_to(&self, other: &Self) -> T {
+        self.distances_to(other).max().unwrap_or_else(T::zero)
+    }
+
+    /// Every point within a chebyshev distance of 1, without allocating.
+    /// The point itself is only included if `include_self` is set.
+    pub fn neighbours(&self, include_self: bool) -> Neighbours<T, N> {
+        Neighbours {
+            centre: *self,
+            index: 0,
+            count: 3usize.pow(N as u32),
+            include_self,
+        }
+    }
+}
+
+/// Iterator over the points surrounding a `VectorN`, see `VectorN::neighbours`.
+///
+/// Each index in `0..3^N` is read as an `N` digit base-3 number, where the digits
+/// 0, 1 and 2 offset the matching component by -1, 0 and +1 respectively.
+#[derive(Debug, Clone)]
+pub struct Neighbours<T: Num + Ord + Copy, const N: usize> {
+    centre: VectorN<T, N>,
+    index: usize,
+    count: usize,
+    include_self: bool,
+}
+
+impl<T, const N: usize> Neighbours<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    /// The index that leaves every component unchanged, i.e. all 1 digits.
+    fn centre_index() -> usize {
+        (0..N).fold(0, |acc, _| acc * 3 + 1)
+    }
+}
+
+impl<T, const N: usize> Iterator for Neighbours<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    type Item = VectorN<T, N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.include_self && self.index == Self::centre_index() {
+            self.index += 1;
+        }
+        if self.index >= self.count {
+            return None;
+        }
+        let mut digits = self.index;
+        let mut coords = self.centre.coords;
+        for c in coords.iter_mut() {
+            match digits % 3 {
+                0 => *c = *c - T::one(),
+                2 => *c = *c + T::one(),
+                _ => {}
+            }
+            digits /= 3;
+        }
+        self.index += 1;
+        Some(VectorN { coords })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let mut remaining = self.count.saturating_sub(self.index);
+        if !self.include_self && self.index <= Self::centre_index() {
+            remaining -= 1;
+        }
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for Neighbours<T, N> where T: Num + Ord + Copy {}
+
+impl<T, const N: usize> Display for VectorN<T, N>
+where
+    T: Num + Ord + Display + Copy,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for (i, c) in self.coords.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<T, const N: usize> Index<usize> for VectorN<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    type Output = T;
+    fn index(&self, index: usize) -> &T {
+        &self.coords[index]
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for VectorN<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.coords[index]
+    }
+}
+
+impl<T, const N: usize> Neg for VectorN<T, N>
+where
+    T: Num + Ord + Copy + Neg + Signed,
+{
+    type Output = Self;
+    fn neg(self) -> Self {
+        self.map(|c| -c)
+    }
+}
+
+impl<T, const N: usize> Add for VectorN<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        self.zip_with(&other, |a, b| a + b)
+    }
+}
+
+impl<T, const N: usize> AddAssign for VectorN<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<T, const N: usize> Sub for VectorN<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        self.zip_with(&other, |a, b| a - b)
+    }
+}
+
+impl<T, const N: usize> SubAssign for VectorN<T, N>
+where
+    T: Num + Ord + Copy,
+{
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn arithmetic() {
+        let a = Vector3::new([1, 2, 3]);
+        let b = Vector3::new([4, -5, 6]);
+        assert_eq!(a + b, Vector3::new([5, -3, 9]));
+        assert_eq!(a - b, Vector3::new([-3, 7, -3]));
+        assert_eq!(-a, Vector3::new([-1, -2, -3]));
+        assert_eq!(a.scaled(2), Vector3::new([2, 4, 6]));
+    }
+
+    #[test]
+    fn distances() {
+        let a = Vector3::new([1, 2, 3]);
+        let b = Vector3::new([4, -5, 6]);
+        assert_eq!(a.man_distance_to(&b), 13);
+        assert_eq!(a.chebyshev_distance_to(&b), 7);
+        assert_eq!(b.man_distance_to_origin(), 15);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Vector3::new([1, -2, 3]).to_string(), "(1, -2, 3)");
+    }
+
+    #[test]
+    fn neighbours_count() {
+        let origin = VectorN::<i32, 4>::zero();
+        assert_eq!(origin.neighbours(false).len(), 80);
+        assert_eq!(origin.neighbours(true).len(), 81);
+        let unique: HashSet<_> = origin.neighbours(false).collect();
+        assert_eq!(unique.len(), 80);
+        assert!(!unique.contains(&origin));
+        assert!(unique.iter().all(|n| n.chebyshev_distance_to(&origin) == 1));
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+/// One instruction of a program that a `Machine` can run.
+///
+/// Implement this for an instruction set (such as day 8's `acc`/`jmp`/`nop`)
+/// to get stepping, breakpoints and tracing for free, and loop detection if
+/// the instruction set allows it.
+pub trait Instruction: Copy + Debug {
+    /// Everything the instructions can change other than the program counter,
+    /// such as registers or an accumulator.
+    type State: Default + Clone + Debug;
+
+    /// Executes the instruction, returning the offset to move the program counter by,
+    /// which is `1` for anything that isn't a jump.
+    fn execute(&self, state: &mut Self::State) -> isize;
+
+    /// Set this if `execute` always moves the program counter by the same offset
+    /// for an instruction, whatever the state. Then an instruction running a second
+    /// time means the program loops forever, and `Machine` halts with `HaltReason::Looped`.
+    /// Otherwise a loop might finish, so the machine keeps going.
+    const DETECT_LOOPS: bool = false;
+
+    /// Every offset `execute` could return, whatever the state.
+    /// Used for static analysis, see `ControlFlowGraph`.
+    fn successor_offsets(&self) -> Vec<isize> {
+        vec![1]
+    }
+}
+
+/// Why a program stopped running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltReason {
+    /// The instruction at `at` was about to run a second time, and the
+    /// instruction set has `DETECT_LOOPS` on, so this would loop forever.
+    Looped { at: usize },
+    /// The program counter moved to just past the last instruction.
+    Terminated,
+    /// The program counter jumped somewhere other than an instruction, or the end.
+    /// A jump past the range of `isize` leaves it at `isize::MIN` or `isize::MAX`.
+    OutOfBounds { pc: isize },
+}
+
+/// Why `Machine::run_until` or `Machine::resume` returned control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pause {
+    Halted(HaltReason),
+    /// The next instruction to run has a breakpoint on it.
+    Breakpoint {
+        at: usize,
+    },
+    /// The condition passed to `run_until` was met.
+    Condition,
+}
+
+/// An instruction that has been executed, with the state it left behind.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct TraceEntry<I: Instruction> {
+    pub pc: usize,
+    pub instruction: I,
+    pub state: I::State,
+}
+
+/// Runs a program of `I` instructions, one step at a time if needed.
+#[derive(Debug, Clone)]
+pub struct Machine<I: Instruction> {
+    program: Vec<I>,
+    pc: isize,
+    state: I::State,
+    /// Which instructions have already run, for loop detection.
+    visited: Vec<bool>,
+    steps: usize,
+    breakpoints: HashSet<usize>,
+    trace: Option<Vec<TraceEntry<I>>>,
+}
+
+#[allow(dead_code)]
+impl<I: Instruction> Machine<I> {
+    pub fn new(program: Vec<I>) -> Self {
+        let len = program.len();
+        Machine {
+            program,
+            pc: 0,
+            state: I::State::default(),
+            visited: vec![false; len],
+            steps: 0,
+            breakpoints: HashSet::new(),
+            trace: None,
+        }
+    }
+
+    /// Start recording every executed instruction, see `trace`.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(vec![]);
+        self
+    }
+
+    /// Back to the start of the program, with a fresh state.
+    /// The program, breakpoints and whether to trace are kept.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.state = I::State::default();
+        self.steps = 0;
+        self.visited.iter_mut().for_each(|v| *v = false);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+    }
+
+    pub fn program(&self) -> &[I] {
+        &self.program
+    }
+
+    /// Replaces the instruction at `index`, returning the old one.
+    /// Panics if `index` is out of bounds.
+    pub fn patch(&mut self, index: usize, instruction: I) -> I {
+        std::mem::replace(&mut self.program[index], instruction)
+    }
+
+    pub fn pc(&self) -> isize {
+        self.pc
+    }
+
+    pub fn state(&self) -> &I::State {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut I::State {
+        &mut self.state
+    }
+
+    /// The number of instructions executed since the last reset.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Every instruction executed since the last reset, if tracing is on.
+    pub fn trace(&self) -> Option<&[TraceEntry<I>]> {
+        self.trace.as_deref()
+    }
+
+    pub fn add_breakpoint(&mut self, index: usize) {
+        self.breakpoints.insert(index);
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> bool {
+        self.breakpoints.remove(&index)
+    }
+
+    /// Why the machine can't run the next instruction, if it can't.
+    pub fn halt_reason(&self) -> Option<HaltReason> {
+        let len = self.program.len() as isize;
+        if self.pc == len {
+            Some(HaltReason::Terminated)
+        } else if self.pc < 0 || self.pc > len {
+            Some(HaltReason::OutOfBounds { pc: self.pc })
+        } else if I::DETECT_LOOPS && self.visited[self.pc as usize] {
+            Some(HaltReason::Looped {
+                at: self.pc as usize,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Runs the next instruction, unless the machine has halted.
+    pub fn step(&mut self) -> Result<(), HaltReason> {
+        if let Some(reason) = self.halt_reason() {
+            return Err(reason);
+        }
+        let pc = self.pc as usize;
+        let instruction = self.program[pc];
+        self.visited[pc] = true;
+        let offset = instruction.execute(&mut self.state);
+        self.pc = match self.pc.checked_add(offset) {
+            Some(pc) => pc,
+            None if offset < 0 => isize::MIN,
+            None => isize::MAX,
+        };
+        self.steps += 1;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(TraceEntry {
+                pc,
+                instruction,
+                state: self.state.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Runs until the program halts, ignoring breakpoints.
+    /// Never returns for a program that loops without `DETECT_LOOPS`.
+    pub fn run(&mut self) -> HaltReason {
+        loop {
+            if let Err(reason) = self.step() {
+                return reason;
+            }
+        }
+    }
+
+    /// Runs until the program halts, reaches a breakpoint, or `condition`
+    /// is true before an instruction runs.
+    ///
+    /// The first instruction always runs, so calling this again after
+    /// pausing makes progress.
+    pub fn run_until<F>(&mut self, mut condition: F) -> Pause
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        let mut first = true;
+        loop {
+            if !first {
+                if let Some(at) = self.pending_breakpoint() {
+                    return Pause::Breakpoint { at };
+                }
+                if condition(self) {
+                    return Pause::Condition;
+                }
+            }
+            first = false;
+            if let Err(reason) = self.step() {
+                return Pause::Halted(reason);
+            }
+        }
+    }
+
+    /// Runs until the program halts or reaches a breakpoint.
+    pub fn resume(&mut self) -> Pause {
+        self.run_until(|_| false)
+    }
+
+    fn pending_breakpoint(&self) -> Option<usize> {
+        if self.pc < 0 {
+            return None;
+        }
+        let pc = self.pc as usize;
+        self.breakpoints.get(&pc).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tiny instruction set: add to a counter, or jump.
+    #[derive(Debug, Clone, Copy)]
+    enum Op {
+        Add(i32),
+        Jump(isize),
+    }
+
+    impl Instruction for Op {
+        type State = i32;
+        const DETECT_LOOPS: bool = true;
+        fn execute(&self, state: &mut i32) -> isize {
+            match self {
+                Op::Add(n) => {
+                    *state += n;
+                    1
+                }
+                Op::Jump(offset) => *offset,
+            }
+        }
+    }
+
+    #[test]
+    fn halting_reasons() {
+        let mut machine = Machine::new(vec![Op::Add(1), Op::Add(2)]);
+        assert_eq!(machine.run(), HaltReason::Terminated);
+        assert_eq!(*machine.state(), 3);
+
+        let mut machine = Machine::new(vec![Op::Add(1), Op::Jump(-1)]);
+        assert_eq!(machine.run(), HaltReason::Looped { at: 0 });
+        assert_eq!(machine.steps(), 2);
+
+        let mut machine = Machine::new(vec![Op::Add(1), Op::Jump(-5)]);
+        assert_eq!(machine.run(), HaltReason::OutOfBounds { pc: -4 });
+        assert_eq!(machine.step(), Err(HaltReason::OutOfBounds { pc: -4 }));
+
+        // the jump overflows an isize
+        let mut machine = Machine::new(vec![Op::Add(1), Op::Jump(isize::MAX)]);
+        assert_eq!(machine.run(), HaltReason::OutOfBounds { pc: isize::MAX });
+        assert_eq!(machine.steps(), 2);
+    }
+
+    /// Counts up, and jumps while the counter is below a limit.
+    #[derive(Debug, Clone, Copy)]
+    enum Counter {
+        Increment,
+        JumpIfBelow(i32, isize),
+    }
+
+    impl Instruction for Counter {
+        type State = i32;
+        fn execute(&self, count: &mut i32) -> isize {
+            match self {
+                Counter::Increment => {
+                    *count += 1;
+                    1
+                }
+                Counter::JumpIfBelow(limit, offset) if *count < *limit => *offset,
+                Counter::JumpIfBelow(_, _) => 1,
+            }
+        }
+    }
+
+    #[test]
+    fn loops_that_branch_on_the_state_can_finish() {
+        let program = vec![Counter::Increment, Counter::JumpIfBelow(3, -1)];
+        let mut machine = Machine::new(program);
+        assert_eq!(machine.run(), HaltReason::Terminated);
+        assert_eq!(*machine.state(), 3);
+        assert_eq!(machine.steps(), 6);
+    }
+
+    #[test]
+    fn breakpoints_and_conditions() {
+        let program = vec![Op::Add(1), Op::Add(1), Op::Add(1), Op::Add(1)];
+        let mut machine = Machine::new(program);
+        machine.add_breakpoint(2);
+        assert_eq!(machine.resume(), Pause::Breakpoint { at: 2 });
+        assert_eq!(*machine.state(), 2);
+        assert_eq!(machine.run_until(|m| *m.state() == 3), Pause::Condition);
+        assert_eq!(machine.pc(), 3);
+        assert_eq!(machine.resume(), Pause::Halted(HaltReason::Terminated));
+    }
+
+    #[test]
+    fn trace_and_reset() {
+        let mut machine = Machine::new(vec![Op::Jump(2), Op::Add(5), Op::Add(1)]).with_trace();
+        machine.run();
+        let pcs: Vec<_> = machine.trace().unwrap().iter().map(|t| t.pc).collect();
+        assert_eq!(pcs, vec![0, 2]);
+        assert_eq!(machine.trace().unwrap()[1].state, 1);
+
+        machine.patch(0, Op::Add(0));
+        machine.reset();
+        assert_eq!(machine.run(), HaltReason::Terminated);
+        assert_eq!(*machine.state(), 6);
+        assert_eq!(machine.trace().unwrap().len(), 3);
+    }
+}
@@ -29,14 +29,6 @@ pub fn ref_keyword() {
 
 // cfg(test) indicates that this code should only be included if testing!
 // this means smaller binaries when not compiling for tests
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn example_test() {
-        assert!(true);
-    }
-}
-
 // this means that #[cfg(test)] can be included in other cases to run code only for unit tests,
 // even if that code itself is not a unit test
 
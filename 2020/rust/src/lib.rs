@@ -3,11 +3,27 @@ extern crate aoc_runner;
 extern crate aoc_runner_derive;
 
 mod common {
+    pub mod assignment;
+    pub mod control_flow;
     pub mod direction;
+    pub mod grammar;
+    pub mod grid;
+    pub mod interval;
+    pub mod modmath;
     pub mod parse_error;
+    pub mod parse_mode;
+    pub mod schedule;
+    pub mod transform;
     pub mod vector2;
+    pub mod vector_n;
+    pub mod vm;
 }
 
+pub mod runner;
+
+pub use common::parse_mode::ParseMode;
+pub use common::schedule::{earliest_alignment, next_departure, Departure, Departures, Schedule};
+
 mod solutions {
     pub mod day01;
     pub mod day02;
@@ -28,6 +44,12 @@ mod solutions {
     pub mod day17;
     pub mod day18;
     pub mod day19;
+    pub mod day20;
+    pub mod day21;
+    pub mod day22;
+    pub mod day23;
+    pub mod day24;
+    pub mod day25;
 }
 
 aoc_lib! { year = 2020 }
@@ -0,0 +1,124 @@
+//! Runs the solutions by day and part, without going through `cargo aoc`.
+
+// `Factory` and its `DayNPartM` traits are generated by `aoc_lib!`
+use crate::*;
+use aoc_runner::{ArcStr, Runner};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// The generator and solver for one part of a day.
+pub type Solver = fn(ArcStr) -> Result<Box<dyn Runner>, Box<dyn Error>>;
+
+macro_rules! solvers {
+    ($($day:literal => $($part:literal: $solver:ident),+;)*) => {
+        /// The solution for the given day and part, if there is one.
+        pub fn solver(day: u32, part: u32) -> Option<Solver> {
+            match (day, part) {
+                $($(($day, $part) => Some(Factory::$solver),)+)*
+                _ => None,
+            }
+        }
+    };
+}
+
+solvers! {
+    1 => 1: day1_part1, 2: day1_part2;
+    2 => 1: day2_part1, 2: day2_part2;
+    3 => 1: day3_part1, 2: day3_part2;
+    4 => 1: day4_part1, 2: day4_part2;
+    5 => 1: day5_part1, 2: day5_part2;
+    6 => 1: day6_part1, 2: day6_part2;
+    7 => 1: day7_part1, 2: day7_part2;
+    8 => 1: day8_part1, 2: day8_part2;
+    9 => 1: day9_part1, 2: day9_part2;
+    10 => 1: day10_part1, 2: day10_part2;
+    11 => 1: day11_part1, 2: day11_part2;
+    12 => 1: day12_part1, 2: day12_part2;
+    13 => 1: day13_part1, 2: day13_part2;
+    14 => 1: day14_part1, 2: day14_part2;
+    15 => 1: day15_part1, 2: day15_part2;
+    16 => 1: day16_part1, 2: day16_part2;
+    17 => 1: day17_part1, 2: day17_part2;
+    18 => 1: day18_part1, 2: day18_part2;
+    19 => 1: day19_part1, 2: day19_part2;
+    20 => 1: day20_part1, 2: day20_part2;
+    21 => 1: day21_part1, 2: day21_part2;
+    22 => 1: day22_part1, 2: day22_part2;
+    23 => 1: day23_part1, 2: day23_part2;
+    24 => 1: day24_part1, 2: day24_part2;
+    25 => 1: day25_part1;
+}
+
+/// Why a part couldn't produce an answer.
+#[derive(Debug)]
+pub enum Failure {
+    /// The generator rejected the input.
+    Parse(Box<dyn Error>),
+    /// The solver ran, but couldn't find an answer.
+    Solve(Box<dyn Error>),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Failure::Parse(e) => write!(f, "parsing failed: {}", e),
+            Failure::Solve(e) => write!(f, "solving failed: {}", e),
+        }
+    }
+}
+
+impl Error for Failure {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Failure::Parse(e) | Failure::Solve(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// The answer to a part, and how long it took to get there.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub answer: String,
+    pub parse_time: Duration,
+    pub solve_time: Duration,
+}
+
+/// Runs the input through the generator and then the solver, timing each.
+///
+/// Trailing newlines are trimmed from the input first, the same as `cargo aoc`.
+pub fn run(solver: Solver, input: &str) -> Result<Outcome, Failure> {
+    let start = Instant::now();
+    let runner = solver(ArcStr::from(input)).map_err(Failure::Parse)?;
+    let parse_time = start.elapsed();
+    let start = Instant::now();
+    let answer = runner.try_run().map_err(Failure::Solve)?.to_string();
+    let solve_time = start.elapsed();
+    Ok(Outcome {
+        answer,
+        parse_time,
+        solve_time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_day_has_a_solution() {
+        for day in 1..=25 {
+            assert!(solver(day, 1).is_some(), "day {}", day);
+        }
+        assert!(solver(25, 2).is_none());
+        assert!(solver(26, 1).is_none());
+    }
+
+    #[test]
+    fn reports_where_it_failed() {
+        let day1 = solver(1, 1).unwrap();
+        assert_eq!(run(day1, "1010\n1010\n").unwrap().answer, "1020100");
+        assert!(matches!(run(day1, "1\nx"), Err(Failure::Parse(_))));
+        assert!(matches!(run(day1, "1\n2"), Err(Failure::Solve(_))));
+    }
+}
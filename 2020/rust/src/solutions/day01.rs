@@ -4,15 +4,18 @@
 // this avoids huge numbers of checks and gives us fast performance searching for the desired
 // element, there's still a nested-for on part 2, but only double instead of triple
 
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::parse_lines;
+
 #[aoc_generator(day1)]
-fn parse_input(input: &str) -> Vec<u32> {
-    let mut vec: Vec<u32> = input.lines().map(|line| line.parse().unwrap()).collect();
+fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    let mut vec: Vec<u32> = parse_lines(input)?;
     vec.sort();
-    vec
+    Ok(vec)
 }
 
 #[aoc(day1, part1)]
-fn part1(input: &Vec<u32>) -> Option<u32> {
+fn part1(input: &[u32]) -> Option<u32> {
     let target = 2020u32;
     for (i, num) in input.iter().enumerate() {
         let remaining_sum = target - num;
@@ -25,7 +28,7 @@ fn part1(input: &Vec<u32>) -> Option<u32> {
 }
 
 #[aoc(day1, part2)]
-fn part2(input: &Vec<u32>) -> Option<u32> {
+fn part2(input: &[u32]) -> Option<u32> {
     let target = 2020u32;
     for (i, num_i) in input.iter().enumerate() {
         let remaining_sum = target - num_i;
@@ -4,7 +4,8 @@
 // I'm really a fan of the ability to write unit tests so easily! This definetly encourages safe
 // code.
 
-use crate::common::parse_error::ParseError;
+use crate::common::parse_error::{parse_match, ParseError};
+use crate::common::parse_mode::parse_lines;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::ops::RangeInclusive;
@@ -77,38 +78,34 @@ impl FromStr for Password {
             static ref REG: Regex = Regex::new(PASSWORD_REGEX).unwrap();
         }
 
-        REG.captures(password_str)
-            .ok_or(ParseError)
-            .and_then(|cap| {
-                Ok(Password::new(
-                    &cap[4],
-                    // we implement From<> for Int error and char error to
-                    // our custom error type
-                    cap[3].parse()?,
-                    cap[1].parse()?,
-                    cap[2].parse()?,
-                ))
-            })
+        let cap = REG.captures(password_str).ok_or_else(|| {
+            ParseError::expected("\"<min>-<max> <char>: <password>\"").with_text(password_str)
+        })?;
+        // we implement From<> for Int error and char error to
+        // our custom error type, parse_match notes where each part came from
+        Ok(Password::new(
+            &cap[4],
+            parse_match(cap.get(3).unwrap())?,
+            parse_match(cap.get(1).unwrap())?,
+            parse_match(cap.get(2).unwrap())?,
+        ))
     }
 }
 
 #[aoc_generator(day2)]
-fn parse_input(input: &str) -> Vec<Password> {
-    input.lines().map(|line| line.parse().unwrap()).collect()
+fn parse_input(input: &str) -> Result<Vec<Password>, ParseError> {
+    parse_lines(input)
 }
 
 #[aoc(day2, part1)]
-fn part1(input: &Vec<Password>) -> usize {
-    input
-        .into_iter()
-        .filter(|pw| pw.is_valid_count_range())
-        .count()
+fn part1(input: &[Password]) -> usize {
+    input.iter().filter(|pw| pw.is_valid_count_range()).count()
 }
 
 #[aoc(day2, part2)]
-fn part2(input: &Vec<Password>) -> usize {
+fn part2(input: &[Password]) -> usize {
     input
-        .into_iter()
+        .iter()
         .filter(|pw| pw.is_valid_char_position())
         .count()
 }
@@ -146,4 +143,20 @@ mod tests {
         let pw = Password::new("cccccccccc", 'c', 2, 9);
         assert!(!pw.is_valid_char_position());
     }
+    #[test]
+    fn parse_error_location() {
+        let input = "1-3 a: abcde\n1-99999999999999999999 b: cdefg";
+        let err = parse_input(input).unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.columns(), Some(2..22));
+        assert_eq!(err.text(), Some("99999999999999999999"));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+    #[test]
+    fn parse_error_shape() {
+        let err = parse_input("1-3 a abcde").unwrap_err();
+        assert_eq!(err.line(), Some(1));
+        assert_eq!(err.text(), Some("1-3 a abcde"));
+        assert!(err.expected_shape().is_some());
+    }
 }
@@ -2,11 +2,12 @@
 // getting the hang of this parsing business now
 // i really like how fast rust is, and how helpful these error messages are
 
+use crate::common::grid::Grid;
 use crate::common::parse_error::ParseError;
 use crate::common::vector2::Vector2;
 use std::convert::TryFrom;
 
-type Input = Vec<Vec<Cell>>;
+type Input = Grid<Cell>;
 
 #[derive(Debug)]
 enum Cell {
@@ -20,34 +21,26 @@ impl TryFrom<char> for Cell {
         match val {
             '.' => Ok(Cell::Empty),
             '#' => Ok(Cell::Tree),
-            _ => Err(ParseError),
+            _ => Err(ParseError::expected("'.' or '#'")),
         }
     }
 }
 
 #[aoc_generator(day3)]
-fn parse_input(input: &str) -> Input {
-    input
-        .lines()
-        .map(|line| {
-            line.chars()
-                .map(|c| Cell::try_from(c).unwrap_or(Cell::Empty))
-                .collect()
-        })
-        .collect()
+fn parse_input(input: &str) -> Result<Input, ParseError> {
+    input.parse()
 }
 
 #[inline]
 fn trees_encountered(input: &Input, slope: &Vector2<usize>) -> usize {
-    let x_wide = input[0].len();
-    let height = input.len();
+    let bounds = Vector2::new(input.width(), input.height());
     let mut pos = Vector2::zero();
     let mut trees = 0;
-    while pos.y < height - 1 {
-        pos += *slope;
-        pos.x %= x_wide; // repeat, so wrap
-        let item = &input[pos.y][pos.x];
-        match item {
+    // stop at the bottom rather than wrapping back to the top
+    while pos.y + slope.y < bounds.y {
+        // the slope repeats to the right, so wrap, y is already in bounds
+        pos = (pos + *slope).rem_euclid(&bounds);
+        match input[pos] {
             Cell::Empty => {}
             Cell::Tree => trees += 1,
         }
@@ -58,7 +51,7 @@ fn trees_encountered(input: &Input, slope: &Vector2<usize>) -> usize {
 #[aoc(day3, part1)]
 fn part1(input: &Input) -> usize {
     let slope = Vector2::new(3, 1);
-    trees_encountered(&input, &slope)
+    trees_encountered(input, &slope)
 }
 
 #[aoc(day3, part2)]
@@ -66,14 +59,45 @@ fn part2(input: &Input) -> usize {
     let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
     slopes
         .iter()
-        .map(|slope| trees_encountered(&input, &Vector2::from_tup(*slope)))
+        .map(|slope| trees_encountered(input, &Vector2::from_tup(*slope)))
         .product()
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#";
+
+    #[test]
+    fn example_part1() {
+        let input = parse_input(EXAMPLE).unwrap();
+        assert_eq!(part1(&input), 7);
+    }
+
+    #[test]
+    fn example_part2() {
+        let input = parse_input(EXAMPLE).unwrap();
+        assert_eq!(part2(&input), 336);
+    }
+
     #[test]
-    fn example_test() {
-        assert!(true);
+    fn steep_slopes_stop_at_the_bottom() {
+        // going down 2 at a time from row 0 of 4 rows reaches row 2, not row 0 again
+        let input = parse_input("#.\n..\n#.\n..").unwrap();
+        assert_eq!(trees_encountered(&input, &Vector2::new(0, 2)), 1);
+        assert_eq!(trees_encountered(&input, &Vector2::new(1, 1)), 1);
+        let empty: Input = Grid::from_cells(0, vec![]).unwrap();
+        assert_eq!(trees_encountered(&empty, &Vector2::new(3, 1)), 0);
     }
 }
@@ -4,6 +4,7 @@
 // complile and store! this gives the best performance!
 
 use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::ops::RangeInclusive;
@@ -73,12 +74,15 @@ impl FromStr for Passport {
     /// assume we are passed a list of the items, in any order
     fn from_str(port_str: &str) -> Result<Self, Self::Err> {
         if port_str.is_empty() {
-            return Err(ParseError);
+            return Err(ParseError::expected("passport fields"));
         }
-        let items = port_str.split(" ").filter(|s| s.len() > 0);
+        let items = port_str.split(' ').filter(|s| !s.is_empty());
         let mut new_port: Passport = Default::default();
         for item in items {
-            let parts: Vec<&str> = item.split(":").map(|l| l.trim()).collect();
+            let parts: Vec<&str> = item.split(':').map(|l| l.trim()).collect();
+            if parts.len() != 2 {
+                return Err(ParseError::expected("\"key:value\"").with_text(item));
+            }
             let ky = parts[0];
             let vl = parts[1];
             match ky {
@@ -90,7 +94,12 @@ impl FromStr for Passport {
                 "ecl" => new_port.ecl = vl.parse().ok(),
                 "pid" => new_port.pid = vl.parse().ok(),
                 "cid" => new_port.cid = vl.parse().ok(),
-                _ => {}
+                _ => {
+                    return Err(ParseError::expected(
+                        "one of byr, iyr, eyr, hgt, hcl, ecl, pid or cid",
+                    )
+                    .with_text(ky))
+                }
             };
         }
         Ok(new_port)
@@ -98,36 +107,38 @@ impl FromStr for Passport {
 }
 
 #[aoc_generator(day4)]
-fn parse_input(input: &str) -> Vec<Passport> {
+fn parse_input(input: &str) -> Result<Vec<Passport>, ParseError> {
     let mut ports = vec![];
     let mut accumulator = "".to_string();
+    // the line each passport starts on, for error reporting
+    let mut start_line = 1;
     // peekable so we don't need to do an awkward extra check after the loop
-    let mut lines = input.lines().map(|l| l.trim()).peekable();
-    while let Some(line) = lines.next() {
+    let mut lines = input.lines().map(|l| l.trim()).enumerate().peekable();
+    while let Some((_, line)) = lines.next() {
         accumulator.push_str(line);
-        accumulator.push_str(" ");
+        accumulator.push(' ');
         match lines.peek() {
             // if the next line ends the passport, add the current
-            Some(&"") | None => {
-                if let Ok(new_port) = Passport::from_str(accumulator.as_str()) {
-                    ports.push(new_port);
-                }
+            Some((_, "")) | None => {
+                ports.push((start_line, Passport::from_str(accumulator.as_str())));
                 accumulator = "".to_string();
                 // consume the separator
-                let _ = lines.next();
+                if let Some((i, _)) = lines.next() {
+                    start_line = i + 2;
+                }
             }
             Some(_) => continue,
         };
     }
-    ports
+    ParseMode::current().collect(ports)
 }
 
 #[aoc(day4, part1)]
-fn part1(input: &Vec<Passport>) -> usize {
-    input.into_iter().filter(|p| p.is_valid()).count()
+fn part1(input: &[Passport]) -> usize {
+    input.iter().filter(|p| p.is_valid()).count()
 }
 
 #[aoc(day4, part2)]
-fn part2(input: &Vec<Passport>) -> usize {
-    input.into_iter().filter(|p| p.is_extended_valid()).count()
+fn part2(input: &[Passport]) -> usize {
+    input.iter().filter(|p| p.is_extended_valid()).count()
 }
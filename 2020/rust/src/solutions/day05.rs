@@ -1,33 +1,38 @@
 // --- Day 5: Binary Boarding ---
 
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
 use std::collections::HashSet;
 
-fn seat_id(boarding_pass: &str) -> u32 {
+fn seat_id(boarding_pass: &str) -> Result<u32, ParseError> {
     boarding_pass
         .chars()
+        .enumerate()
         // convert char to a bit
-        .filter_map::<u8, _>(|c| match c {
-            'F' | 'L' => Some(0),
-            'B' | 'R' => Some(1),
-            _ => None,
+        .map(|(i, c)| match c {
+            'F' | 'L' => Ok(0),
+            'B' | 'R' => Ok(1),
+            _ => Err(ParseError::expected("one of F, B, L or R")
+                .with_text(c.to_string())
+                .at_columns(i..i + 1)),
         })
         // convert bit list to a number, the seat id pops out!
-        .fold(0, |acc, b| acc * 2 + b as u32)
+        .try_fold(0, |acc, b| Ok(acc * 2 + b?))
 }
 
 #[aoc_generator(day5)]
-fn parse_input(input: &str) -> Vec<u32> {
-    input.lines().map(|line| seat_id(line)).collect()
+fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    ParseMode::current().parse_lines_with(input, seat_id)
 }
 
 #[aoc(day5, part1)]
-fn part1(input: &Vec<u32>) -> u32 {
-    input.into_iter().max().unwrap_or(&0).clone()
+fn part1(input: &[u32]) -> u32 {
+    *input.iter().max().unwrap_or(&0)
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &Vec<u32>) -> Option<u32> {
-    let seen = input.into_iter().collect::<HashSet<_>>();
+fn part2(input: &[u32]) -> Option<u32> {
+    let seen = input.iter().collect::<HashSet<_>>();
     // rust iter note ->
     // .iter() ALWAYS returns a reference (&T)
     // eliding .iter() calls .into_iter() (see docs for more info)
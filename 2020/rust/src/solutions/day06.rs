@@ -1,15 +1,27 @@
 // --- Day 6: Custom Customs ---
 // not the most elegant solution, ideally this would be more functional
 
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
 use std::collections::{HashMap, HashSet};
 type Input = Vec<Vec<char>>;
 
+/// The questions answered 'yes' by a single person, a-z.
+fn answers(line: &str) -> Result<Vec<char>, ParseError> {
+    match line.find(|c: char| !c.is_ascii_lowercase()) {
+        Some(i) => Err(ParseError::expected("questions a-z")
+            .with_text(line)
+            .at_columns(i..i + 1)),
+        None => Ok(line.chars().collect()),
+    }
+}
+
 #[aoc_generator(day6)]
-fn parse_input(input: &str) -> Input {
-    let mut vec: Input = input.lines().map(|line| line.chars().collect()).collect();
+fn parse_input(input: &str) -> Result<Input, ParseError> {
+    let mut vec: Input = ParseMode::current().parse_lines_with(input, answers)?;
     // add empty to the end for the last
     vec.push(vec![]);
-    vec
+    Ok(vec)
 }
 
 #[aoc(day6, part1)]
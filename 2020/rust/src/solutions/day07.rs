@@ -1,6 +1,7 @@
 // --- Day 7: Handy Haversacks ---
 
-use crate::common::parse_error::ParseError;
+use crate::common::parse_error::{parse_match, ParseError};
+use crate::common::parse_mode::ParseMode;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -13,49 +14,58 @@ type BagID = String;
 type BagCarosel = HashMap<BagID, HashMap<BagID, u32>>;
 
 /**
- * returns `None` if a child cannot be parsed
+ * returns `None` if the bag contains no other bags
  */
-fn child_bag(string: &str) -> Option<(BagID, u32)> {
+fn child_bag(string: &str) -> Result<Option<(BagID, u32)>, ParseError> {
     lazy_static! {
         // bag(s) <- optionally match the 's'
         static ref CHILD_REGEX: Regex = Regex::from_str(r"^(\d+)\s(.*)\sbags?$").unwrap();
     }
     // there may be 'contains no other bags'
-    // so we question mark unwrap the optional to return 'None'
-    // in the event that the regex did not match
-    let captures = CHILD_REGEX.captures(string)?;
-    let amount = captures.get(1)?.as_str().parse().unwrap();
-    let name = captures.get(2)?.as_str().to_string();
-    Some((name, amount))
+    if string == "no other bags" {
+        return Ok(None);
+    }
+    let captures = CHILD_REGEX.captures(string).ok_or_else(|| {
+        ParseError::expected("\"<count> <bag> bags\" or \"no other bags\"").with_text(string)
+    })?;
+    let amount = parse_match(captures.get(1).unwrap())?;
+    let name = captures.get(2).unwrap().as_str().to_string();
+    Ok(Some((name, amount)))
 }
 
 /// assume we are passed a list of the items, in any order
 fn parent_bag(bag: &str) -> Result<(BagID, HashMap<BagID, u32>), ParseError> {
     if bag.is_empty() {
-        return Err(ParseError);
+        return Err(ParseError::expected("a bag rule"));
     }
     lazy_static! {
         // bag(s) <- optionally match the 's'
         static ref BAG_REGEX: Regex =
             Regex::from_str(r"^(.*)\sbags?\scontain\s(.*)\.$").unwrap();
     }
-    let captures = BAG_REGEX.captures(bag).ok_or(ParseError)?;
-    let our_bag = captures.get(1).ok_or(ParseError)?.as_str();
+    let captures = BAG_REGEX.captures(bag).ok_or_else(|| {
+        ParseError::expected("\"<bag> bags contain <children>.\"").with_text(bag)
+    })?;
+    let our_bag = captures.get(1).ok_or_else(ParseError::new)?.as_str();
 
     let mut children = HashMap::default();
-    let other_bags = captures.get(2).ok_or(ParseError)?.as_str();
+    let other_bags = captures.get(2).ok_or_else(ParseError::new)?;
     const BAG_LIST_DELIMITER: &str = ", ";
-    for other in other_bags.split(BAG_LIST_DELIMITER) {
-        if let Some((name, count)) = child_bag(other) {
+    let mut offset = other_bags.start();
+    for other in other_bags.as_str().split(BAG_LIST_DELIMITER) {
+        let child = child_bag(other).map_err(|e| e.at_columns(offset..offset + other.len()))?;
+        if let Some((name, count)) = child {
             children.insert(name, count);
         }
+        offset += other.len() + BAG_LIST_DELIMITER.len();
     }
     Ok((BagID::from(our_bag), children))
 }
 
 #[aoc_generator(day7)]
-fn parse_input(input: &str) -> BagCarosel {
-    input.lines().filter_map(|line| parent_bag(line).ok()).collect()
+fn parse_input(input: &str) -> Result<BagCarosel, ParseError> {
+    let bags = ParseMode::current().parse_lines_with(input, parent_bag)?;
+    Ok(bags.into_iter().collect())
 }
 
 #[aoc(day7, part1)]
@@ -1,13 +1,16 @@
 // --- Day 8: Handheld Halting ---
 
-use crate::common::parse_error::ParseError;
+use crate::common::control_flow::find_patch;
+use crate::common::parse_error::{parse_match, ParseError};
+use crate::common::parse_mode::parse_lines;
+use crate::common::vm::{HaltReason, Instruction, Machine};
 use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashSet;
-use std::convert::TryFrom;
+use regex::{Match, Regex};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Operation {
     Acc(isize),
     Jmp(isize),
@@ -21,137 +24,285 @@ impl FromStr for Operation {
         lazy_static! {
             static ref OP_REGEX: Regex = Regex::from_str(r"^(\w+)\s+([-+]\d+)$").unwrap();
         }
-        let captures = OP_REGEX.captures(s).ok_or(ParseError)?;
-        let op_name = captures.get(1).ok_or(ParseError)?.as_str();
-        let shift: isize = captures.get(2).ok_or(ParseError)?.as_str().parse()?;
-        match op_name {
+        let captures = OP_REGEX
+            .captures(s)
+            .ok_or_else(|| ParseError::expected("\"<op> <+/-offset>\"").with_text(s))?;
+        let shift: isize = parse_match(captures.get(2).unwrap())?;
+        Operation::new(captures.get(1).unwrap(), shift)
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Operation::Acc(dist) => write!(f, "acc {:+}", dist),
+            Operation::Jmp(dist) => write!(f, "jmp {:+}", dist),
+            Operation::Nop(dist) => write!(f, "nop {:+}", dist),
+        }
+    }
+}
+
+impl Operation {
+    /// The operation called `op_name`, with the columns of the name for any error.
+    fn new(op_name: Match, shift: isize) -> Result<Operation, ParseError> {
+        match op_name.as_str() {
             "acc" => Ok(Operation::Acc(shift)),
             "jmp" => Ok(Operation::Jmp(shift)),
             "nop" => Ok(Operation::Nop(shift)),
-            _ => Err(ParseError),
+            _ => Err(ParseError::expected("one of acc, jmp or nop")
+                .with_text(op_name.as_str())
+                .at_columns(op_name.start()..op_name.end())),
         }
     }
-}
 
-#[derive(Debug)]
-enum ComputerFinished {
-    Looped,
-    Terminated,
+    /// The jmp/nop swap that might fix the program, `None` for acc.
+    fn flipped(&self) -> Option<Operation> {
+        match *self {
+            Operation::Acc(_) => None,
+            Operation::Jmp(dist) => Some(Operation::Nop(dist)),
+            Operation::Nop(dist) => Some(Operation::Jmp(dist)),
+        }
+    }
 }
 
-#[derive(Debug)]
-struct Computer {
-    acc: isize,
-    pc: usize,
-    program: Vec<Operation>,
-    pc_seen: HashSet<usize>,
-    loop_flag: bool,
-}
+impl Instruction for Operation {
+    /// The accumulator.
+    type State = isize;
+    /// Only `jmp` moves anywhere but the next instruction, and it ignores the accumulator.
+    const DETECT_LOOPS: bool = true;
 
-impl Computer {
-    fn new(program: Vec<Operation>) -> Self {
-        Self {
-            acc: 0,
-            pc: 0,
-            program,
-            pc_seen: HashSet::default(),
-            loop_flag: false,
-        }
-    }
-    fn reset_run_state(&mut self) {
-        self.acc = 0;
-        self.pc = 0;
-        self.loop_flag = false;
-        self.pc_seen.clear();
-    }
-    fn run_op(&mut self, op: &Operation) {
-        use Operation::*;
-        match op {
-            Acc(dist) => {
-                self.pc += 1;
-                self.acc += dist;
-            }
-            Jmp(dist) => {
-                // program counter < 0 is a program error, so nothing we can do
-                let pc = self.pc as isize + dist;
-                self.pc = usize::try_from(pc).unwrap();
-            }
-            Nop(_) => {
-                self.pc += 1;
+    fn execute(&self, acc: &mut isize) -> isize {
+        match self {
+            Operation::Acc(dist) => {
+                *acc += dist;
+                1
             }
+            Operation::Jmp(dist) => *dist,
+            Operation::Nop(_) => 1,
         }
-        if self.pc_seen.contains(&self.pc) {
-            self.loop_flag = true;
+    }
+
+    fn successor_offsets(&self) -> Vec<isize> {
+        match self {
+            Operation::Jmp(dist) => vec![*dist],
+            Operation::Acc(_) | Operation::Nop(_) => vec![1],
         }
-        self.pc_seen.insert(self.pc);
     }
-    /// Get the op that the program counter is currently pointing to.
-    fn current_op(&self) -> Option<Operation> {
-        // program counter should only ever be positive, otherwise it's a program error
-        let op = self.program.get(self.pc)?.clone();
-        Some(op)
+}
+
+/// Builds a program from source that can also have `# comments` and labels.
+///
+/// A label is defined with `name:` at the start of a line, and can be used in
+/// place of the offset for jmp and nop, which then point to the next
+/// instruction after the label.
+///
+/// ```text
+/// start:  acc +1
+///         jmp start   # loops forever
+/// ```
+#[allow(dead_code)]
+fn assemble(source: &str) -> Result<Vec<Operation>, ParseError> {
+    lazy_static! {
+        static ref LINE_REGEX: Regex =
+            Regex::new(r"^\s*(?:([A-Za-z_]\w*)\s*:)?\s*(?:(\w+)\s+([-+]?\w+))?\s*$").unwrap();
+        static ref OFFSET_REGEX: Regex = Regex::new(r"^[-+]\d+$").unwrap();
     }
-    /// For the index in the `program`, invert jmp => nop or nop => jmp accordingly
-    fn invert_jmp_or_nop(&mut self, index: usize) {
-        use Operation::*;
-        match self.program[index] {
-            Acc(_) => {}
-            Jmp(dist) => {
-                self.program[index] = Nop(dist);
-            }
-            Nop(dist) => {
-                self.program[index] = Jmp(dist);
+    // first pass, find where the labels point and leave the operands for later
+    let mut labels = HashMap::new();
+    let mut unresolved = vec![];
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let code = line.split('#').next().unwrap();
+        let captures = LINE_REGEX.captures(code).ok_or_else(|| {
+            ParseError::expected("\"[label:] [<op> <+/-offset or label>] [# comment]\"")
+                .with_text(line)
+                .at_line(line_number)
+        })?;
+        if let Some(label) = captures.get(1) {
+            if labels.insert(label.as_str(), unresolved.len()).is_some() {
+                return Err(ParseError::expected("each label to be defined once")
+                    .with_text(label.as_str())
+                    .at_columns(label.start()..label.end())
+                    .at_line(line_number));
             }
         }
+        if let (Some(op_name), Some(operand)) = (captures.get(2), captures.get(3)) {
+            unresolved.push((line_number, op_name, operand));
+        }
     }
-    /// Run the computer until one of the possible outcomes occurs.
-    fn run(&mut self) -> ComputerFinished {
-        loop {
-            match self.current_op() {
-                Some(op) => {
-                    self.run_op(&op);
-                    if self.loop_flag {
-                        return ComputerFinished::Looped;
-                    }
-                }
-                // assume if we can't get an op, it's out of range,
-                // therefore is terminated
-                None => return ComputerFinished::Terminated,
+    let mut program = vec![];
+    for (index, (line_number, op_name, operand)) in unresolved.into_iter().enumerate() {
+        let shift = if OFFSET_REGEX.is_match(operand.as_str()) {
+            parse_match(operand)
+        } else if op_name.as_str() == "acc" {
+            Err(
+                ParseError::expected("a +/-number to add to the accumulator")
+                    .with_text(operand.as_str())
+                    .at_columns(operand.start()..operand.end()),
+            )
+        } else {
+            match labels.get(operand.as_str()) {
+                Some(target) => Ok(*target as isize - index as isize),
+                None => Err(ParseError::expected("+/-offset or a defined label")
+                    .with_text(operand.as_str())
+                    .at_columns(operand.start()..operand.end())),
             }
+        };
+        let op = shift.and_then(|shift| Operation::new(op_name, shift));
+        program.push(op.map_err(|e| e.at_line(line_number))?);
+    }
+    Ok(program)
+}
+
+/// The program as `assemble` source, with a label at every jump target.
+/// Jumps are commented with the original offset, and any that leave the
+/// program are kept as offsets.
+#[allow(dead_code)]
+fn disassemble(program: &[Operation]) -> String {
+    let target = |index: usize, dist: isize| {
+        let target = index as isize + dist;
+        if target >= 0 && target <= program.len() as isize {
+            Some(target as usize)
+        } else {
+            None
         }
+    };
+    let targets: BTreeSet<usize> = program
+        .iter()
+        .enumerate()
+        .filter_map(|(index, op)| match op {
+            Operation::Jmp(dist) => target(index, *dist),
+            _ => None,
+        })
+        .collect();
+    let mut listing = String::new();
+    for index in 0..=program.len() {
+        if targets.contains(&index) {
+            listing.push_str(&format!("l{}:\n", index));
+        }
+        let line = match program.get(index) {
+            Some(Operation::Jmp(dist)) => match target(index, *dist) {
+                Some(to) => format!("jmp l{:<8}# {:+}", to, dist),
+                None => format!("jmp {:<9}# out of bounds", format!("{:+}", dist)),
+            },
+            Some(op) => op.to_string(),
+            None => break,
+        };
+        listing.push_str(&format!("    {}\n", line));
+    }
+    listing
+}
+
+/// The index of the one jmp/nop that has to be swapped for the program to
+/// terminate, and the accumulator it terminates with.
+fn repair(program: &[Operation]) -> Option<(usize, isize)> {
+    let (index, fixed) = find_patch(program, Operation::flipped)?;
+    let mut console = Machine::new(program.to_vec());
+    console.patch(index, fixed);
+    match console.run() {
+        HaltReason::Terminated => Some((index, *console.state())),
+        _ => None,
     }
 }
 
 #[aoc_generator(day8)]
-fn parse_input(input: &str) -> Vec<Operation> {
-    input.lines().filter_map(|l| l.parse().ok()).collect()
+fn parse_input(input: &str) -> Result<Vec<Operation>, ParseError> {
+    parse_lines(input)
 }
 
 #[aoc(day8, part1)]
-fn part1(input: &Vec<Operation>) -> Option<isize> {
-    let mut comp = Computer::new(input.clone());
-    match comp.run() {
-        ComputerFinished::Looped => Some(comp.acc),
-        ComputerFinished::Terminated => None,
+fn part1(input: &[Operation]) -> Option<isize> {
+    let mut console = Machine::new(input.to_vec());
+    match console.run() {
+        HaltReason::Looped { .. } => Some(*console.state()),
+        HaltReason::Terminated | HaltReason::OutOfBounds { .. } => None,
     }
 }
 
 #[aoc(day8, part2)]
-fn part2(input: &Vec<Operation>) -> Option<isize> {
-    // Attempts every possible swap until we find one that terminates.
-    // We know that if the computer loops at any point, it's an infinite
-    // loop, so that run can be disregarded.
-    let mut comp = Computer::new(input.clone());
-    for ins_index in 0..input.len() {
-        comp.invert_jmp_or_nop(ins_index);
-        match comp.run() {
-            ComputerFinished::Looped => {}
-            ComputerFinished::Terminated => return Some(comp.acc),
-        }
-        // we looped, so try the next instruction.
-        // but first reset the state.
-        comp.invert_jmp_or_nop(ins_index);
-        comp.reset_run_state();
+fn part2(input: &[Operation]) -> Option<isize> {
+    repair(input).map(|(_, acc)| acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_unknown_op() {
+        let err = parse_input("nop +0\nacc +1\nmul +2").unwrap_err();
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(err.columns(), Some(0..3));
+        assert_eq!(err.text(), Some("mul"));
+    }
+
+    #[test]
+    fn negative_jump_is_out_of_bounds() {
+        let program = parse_input("nop +0\njmp -2").unwrap();
+        let mut console = Machine::new(program);
+        assert_eq!(console.run(), HaltReason::OutOfBounds { pc: -1 });
+    }
+
+    #[test]
+    fn repairs_example() {
+        let program =
+            parse_input("nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6")
+                .unwrap();
+        assert_eq!(repair(&program), Some((7, 8)));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let source = "nop +0\nacc -99\njmp +4";
+        let program = parse_input(source).unwrap();
+        let listing: Vec<String> = program.iter().map(|op| op.to_string()).collect();
+        assert_eq!(listing.join("\n"), source);
+    }
+
+    #[test]
+    fn assembles_labels_and_comments() {
+        let source = "\
+            # counts to 3, then stops
+            start:  acc +1
+                    jmp check
+            # not reached
+            check:
+                    nop start   # labels work for nop too
+                    jmp end
+            end:";
+        let program = assemble(source).unwrap();
+        use Operation::*;
+        assert_eq!(program, vec![Acc(1), Jmp(1), Nop(-2), Jmp(1)]);
+    }
+
+    #[test]
+    fn assembler_errors() {
+        let err = assemble("jmp +1\njmp nowhere").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.columns(), Some(4..11));
+        let err = assemble("a: nop +0\na: nop +0").unwrap_err();
+        assert_eq!(err.text(), Some("a"));
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(assemble("x: acc x").unwrap_err().columns(), Some(7..8));
+        assert_eq!(assemble("x: mul x").unwrap_err().text(), Some("mul"));
+    }
+
+    #[test]
+    fn disassembles_to_the_same_program() {
+        let program = parse_input("nop +0\njmp +2\njmp +5\nacc +3\njmp -2").unwrap();
+        let listing = disassemble(&program);
+        assert_eq!(
+            listing,
+            "    nop +0\n    jmp l3       # +2\nl2:\n    jmp +5       # out of bounds\n\
+             l3:\n    acc +3\n    jmp l2       # -2\n"
+        );
+        assert_eq!(assemble(&listing).unwrap(), program);
+    }
+
+    #[test]
+    fn parse_error_bad_offset() {
+        let err = parse_input("jmp 4").unwrap_err();
+        assert_eq!(err.line(), Some(1));
+        assert_eq!(err.text(), Some("jmp 4"));
     }
-    None
 }
@@ -1,18 +1,22 @@
 // --- Day 9: Encoding Error ---
 
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::parse_lines;
+
 #[aoc_generator(day9)]
-fn parse_input(input: &str) -> Vec<i32> {
-    input.lines().filter_map(|l| l.parse().ok()).collect()
+fn parse_input(input: &str) -> Result<Vec<i64>, ParseError> {
+    parse_lines(input)
 }
 
 /// For part 1, the look-back size.
 const LOOKBACK_SIZE: usize = 25;
 
-fn invalid_number(input: &Vec<i32>) -> Option<i32> {
-    'i: for i in LOOKBACK_SIZE..input.len() {
+/// The first number that isn't the sum of 2 of the `lookback` numbers before it.
+fn invalid_number(input: &[i64], lookback: usize) -> Option<i64> {
+    'i: for i in lookback..input.len() {
         let current = input[i];
-        for j in (i - LOOKBACK_SIZE)..=i {
-            for k in (i - LOOKBACK_SIZE)..=i {
+        for j in (i - lookback)..=i {
+            for k in (i - lookback)..=i {
                 if j == k {
                     continue;
                 }
@@ -27,21 +31,27 @@ fn invalid_number(input: &Vec<i32>) -> Option<i32> {
 }
 
 #[aoc(day9, part1)]
-fn part1(input: &Vec<i32>) -> Option<i32> {
-    invalid_number(input)
+fn part1(input: &[i64]) -> Option<i64> {
+    invalid_number(input, LOOKBACK_SIZE)
 }
 
 /// For part 2. The minimum number of contiguous elements that make up the result.
 const MIN_CONTIGUOUS_LEN: usize = 2;
 
 #[aoc(day9, part2)]
-fn part2(input: &Vec<i32>) -> Option<i32> {
-    let target_number = invalid_number(input)?;
+fn part2(input: &[i64]) -> Option<i64> {
+    encryption_weakness(input, LOOKBACK_SIZE)
+}
+
+/// The sum of the smallest and largest numbers in the shortest contiguous run
+/// that adds up to the invalid number.
+fn encryption_weakness(input: &[i64], lookback: usize) -> Option<i64> {
+    let target_number = invalid_number(input, lookback)?;
     for size in MIN_CONTIGUOUS_LEN..input.len() {
         for upper in size..input.len() {
             let lower = upper - size;
             let slice = &input[lower..upper];
-            if slice.iter().sum::<i32>() == target_number {
+            if slice.iter().sum::<i64>() == target_number {
                 let min = slice.iter().min()?;
                 let max = slice.iter().max()?;
                 return Some(min + max);
@@ -50,3 +60,37 @@ fn part2(input: &Vec<i32>) -> Option<i32> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+35
+20
+15
+25
+47
+40
+62
+55
+65
+95
+102
+117
+150
+182
+127
+219
+299
+277
+309
+576";
+
+    #[test]
+    fn example() {
+        let input = parse_input(EXAMPLE).unwrap();
+        assert_eq!(invalid_number(&input, 5), Some(127));
+        assert_eq!(encryption_weakness(&input, 5), Some(62));
+    }
+}
@@ -1,20 +1,26 @@
 // --- Day 10: Adapter Array ---
 
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::parse_lines;
 use std::collections::HashMap;
 
 #[aoc_generator(day10)]
-fn parse_input(input: &str) -> Vec<i64> {
-    let mut vec: Vec<i64> = input.lines().filter_map(|l| l.parse().ok()).collect();
+fn parse_input(input: &str) -> Result<Vec<i64>, ParseError> {
+    let mut vec: Vec<i64> = parse_lines(input)?;
+    let highest = *vec
+        .iter()
+        .max()
+        .ok_or_else(|| ParseError::expected("at least one adapter"))?;
     // the 'ground plug'
     vec.push(0);
     // the device
-    vec.push(vec.iter().max().unwrap() + 3);
+    vec.push(highest + 3);
     vec.sort();
-    vec
+    Ok(vec)
 }
 
 #[aoc(day10, part1)]
-fn part1(input: &Vec<i64>) -> Option<i64> {
+fn part1(input: &[i64]) -> Option<i64> {
     let mut n1 = 0;
     let mut n3 = 0;
     for idx in 1..input.len() {
@@ -29,10 +35,10 @@ fn part1(input: &Vec<i64>) -> Option<i64> {
 }
 
 #[aoc(day10, part2)]
-fn part2(input: &Vec<i64>) -> Option<i64> {
+fn part2(input: &[i64]) -> Option<i64> {
     let mut counts = HashMap::new();
     counts.insert(0, 1); // 1 way to reach zero
-    for adapter in &input.as_slice()[1..] {
+    for adapter in &input[1..] {
         // add number of ways to 'bridge gap' to this connector
         // there's a difference of either 1,2,3 allowed, so any possible
         // combination can bridge this gap
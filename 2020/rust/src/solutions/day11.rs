@@ -1,50 +1,42 @@
 // --- Day 11: Seating System ---
 
+use crate::common::direction::Direction8;
+use crate::common::grid::Grid;
+use crate::common::parse_error::ParseError;
 use crate::common::vector2::Vector2;
-use std::convert::From;
+use std::convert::TryFrom;
+use std::str::FromStr;
 
 enum Part {
     One,
     Two,
 }
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
 enum Position {
     Seat(bool),
+    #[default]
     Floor,
 }
 
-impl Default for Position {
-    fn default() -> Self {
-        Position::Floor
-    }
-}
-
 impl Position {
     fn is_occupied(&self) -> bool {
-        match &self {
-            Position::Seat(true) => true,
-            _ => false,
-        }
+        matches!(self, Position::Seat(true))
     }
     fn never_changes(&self) -> bool {
-        match &self {
-            Position::Floor => true,
-            _ => false,
-        }
+        matches!(self, Position::Floor)
     }
 }
 
-impl From<char> for Position {
-    // From<T> must succeed
-    // if there was a possibility of graceful failure, use TryFrom instead
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Position {
+    type Error = ParseError;
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         use Position::*;
         match c {
-            'L' => Seat(false),
-            '#' => Seat(true),
-            '.' => Floor,
-            _ => panic!("bad input"),
+            'L' => Ok(Seat(false)),
+            '#' => Ok(Seat(true)),
+            '.' => Ok(Floor),
+            _ => Err(ParseError::expected("'L', '#' or '.'")),
         }
     }
 }
@@ -53,77 +45,37 @@ impl From<char> for Position {
 // copy -> implicit, should only be used for non-expensive things
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct WaitingRoom {
-    floor: Vec<Vec<Position>>,
+    floor: Grid<Position>,
     changed_flag: bool,
 }
 
-impl From<&str> for WaitingRoom {
-    fn from(input: &str) -> Self {
-        let positions = input
-            .lines()
-            .map(|l| l.chars().map(|c| Position::from(c)).collect())
-            .collect();
-        Self {
-            floor: positions,
+impl FromStr for WaitingRoom {
+    type Err = ParseError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            floor: input.parse()?,
             changed_flag: false,
-        }
-    }
-}
-
-impl WaitingRoom {
-    fn rows(&self) -> i32 {
-        self.floor.len() as i32
-    }
-    fn cols(&self) -> i32 {
-        self.floor[0].len() as i32
+        })
     }
 }
 
 impl WaitingRoom {
-    fn is_valid_position(&self, v: Vector2<i32>) -> bool {
-        if v.x < 0 || v.y < 0 {
-            return false;
-        }
-        if v.x > self.rows() || v.y > self.cols() {
-            return false;
-        }
-        return true;
-    }
     /// Get the position at the given coordinates.
+    /// Anything outside of the room is considered to be floor.
     fn position(&self, v: Vector2<i32>) -> Position {
-        if v.x < 0 || v.y < 0 || v.x >= self.rows() || v.y >= self.cols() {
-            return Position::Floor;
-        }
-        match &self.floor.get(v.x as usize) {
-            Some(row) => {
-                if let Some(pos) = row.get(v.y as usize) {
-                    *pos
-                } else {
-                    Position::default()
-                }
-            }
-            _ => Position::default(),
-        }
+        self.floor.get(v).copied().unwrap_or_default()
     }
     /// Gets the new state for a given x,y.
-    fn new_state_part_1(&self, v: Vector2<i32>) -> Position {
-        let current = self.position(v);
+    fn new_state_part_1(&self, v: Vector2<usize>) -> Position {
+        let current = self.floor[v];
         if current.never_changes() {
             return current;
         }
-        let mut adjacent_occupied = 0;
-        for i in -1..=1 as i32 {
-            for j in -1..=1 as i32 {
-                if i == 0 && j == 0 {
-                    continue;
-                }
-                let vec = v + Vector2::new(i, j);
-                let pos = self.position(vec);
-                if pos.is_occupied() {
-                    adjacent_occupied += 1;
-                }
-            }
-        }
+        let adjacent_occupied = self
+            .floor
+            .neighbours8(v)
+            .filter(|(_, pos)| pos.is_occupied())
+            .count();
         match current {
             Position::Seat(false) if adjacent_occupied == 0 => Position::Seat(true),
             Position::Seat(true) if adjacent_occupied >= 4 => Position::Seat(false),
@@ -135,7 +87,7 @@ impl WaitingRoom {
         let mut current = start;
         loop {
             current += repeat;
-            if !self.is_valid_position(current) {
+            if !self.floor.contains(current) {
                 // if it reached an invalid position, the line is not occupied
                 return false;
             }
@@ -147,24 +99,15 @@ impl WaitingRoom {
         }
     }
 
-    fn new_state_part_2(&self, v: Vector2<i32>) -> Position {
-        let current = self.position(v);
+    fn new_state_part_2(&self, v: Vector2<usize>) -> Position {
+        let current = self.floor[v];
         if current.never_changes() {
             return current;
         }
-        let directions = [
-            Vector2::new(1, 0),
-            Vector2::new(-1, 0),
-            Vector2::new(0, 1),
-            Vector2::new(0, -1),
-            Vector2::new(1, 1),
-            Vector2::new(1, -1),
-            Vector2::new(-1, 1),
-            Vector2::new(-1, -1),
-        ];
-        let adjacent_occupied = directions
+        let start = v.try_cast().expect("grid positions fit in an i32");
+        let adjacent_occupied = Direction8::ALL
             .iter()
-            .map(|d| self.is_occupied_in_direction(v, *d))
+            .map(|d| self.is_occupied_in_direction(start, d.vector()))
             .filter(|o| o == &true)
             .count();
         match current {
@@ -177,35 +120,28 @@ impl WaitingRoom {
     fn round(&mut self, part: Part) {
         self.changed_flag = false;
         let old = self.clone();
-        for i in 0..self.rows() {
-            for j in 0..self.cols() {
-                let vec = Vector2::new(i, j);
-                let old_state = old.position(vec);
-                let new_state = match part {
-                    Part::One => old.new_state_part_1(vec),
-                    Part::Two => old.new_state_part_2(vec),
-                };
-                self.floor[i as usize][j as usize] = new_state;
-                if old_state != new_state {
-                    self.changed_flag = true;
-                }
+        for vec in old.floor.positions() {
+            let old_state = old.floor[vec];
+            let new_state = match part {
+                Part::One => old.new_state_part_1(vec),
+                Part::Two => old.new_state_part_2(vec),
+            };
+            self.floor[vec] = new_state;
+            if old_state != new_state {
+                self.changed_flag = true;
             }
         }
     }
 
     /// how many seats are occupied?
     fn occupied(&self) -> usize {
-        self.floor
-            .iter()
-            .flatten()
-            .filter(|pos| pos.is_occupied())
-            .count()
+        self.floor.iter().filter(|pos| pos.is_occupied()).count()
     }
 }
 
 #[aoc_generator(day11)]
-fn parse_input(input: &str) -> WaitingRoom {
-    WaitingRoom::from(input)
+fn parse_input(input: &str) -> Result<WaitingRoom, ParseError> {
+    input.parse()
 }
 
 #[aoc(day11, part1)]
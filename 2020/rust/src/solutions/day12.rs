@@ -8,28 +8,12 @@
 // Corresponds to Left, Right, Up, Down.
 // And the 2d plane x-axis and y-axis.
 
-use crate::common::direction::Direction;
+use crate::common::direction::{Direction, Rotation};
 use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
 use crate::common::vector2::Vector2;
 use std::convert::TryFrom;
 
-#[derive(Debug, Clone, Copy)]
-enum Rotation {
-    Left,
-    Right,
-    Reverse,
-}
-
-impl Direction {
-    fn rotated(&self, rot: Rotation) -> Self {
-        match rot {
-            Rotation::Left => self.rotated_left(),
-            Rotation::Right => self.rotated_right(),
-            Rotation::Reverse => self.reversed(),
-        }
-    }
-}
-
 #[derive(Debug, Clone, Copy)]
 enum Action {
     Rotate(Rotation),
@@ -41,58 +25,56 @@ impl TryFrom<&str> for Action {
     type Error = ParseError;
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         use Action::*;
-        let chars: Vec<_> = s.chars().into_iter().collect();
-        let first = chars[0];
-        let amount = chars.iter().skip(1).collect::<String>().parse().unwrap();
+        let first = s
+            .chars()
+            .next()
+            .ok_or_else(|| ParseError::expected("an action"))?;
+        let rest = &s[first.len_utf8()..];
+        let amount: i32 = rest.parse().map_err(|e| {
+            ParseError::from(e)
+                .with_text(rest)
+                .at_columns(first.len_utf8()..s.len())
+        })?;
         match first {
             'F' => Ok(MoveForward(amount)),
-            'L' => {
-                let degrees = amount % 360;
-                let rotation = match degrees {
-                    0 => return Err(ParseError),
-                    90 => Rotation::Left,
-                    180 => Rotation::Reverse,
-                    270 => Rotation::Right,
-                    _ => panic!(),
-                };
-                Ok(Rotate(rotation))
-            }
-            'R' => {
-                let degrees = amount % 360;
-                let rotation = match degrees {
-                    0 => return Err(ParseError),
-                    90 => Rotation::Right,
-                    180 => Rotation::Reverse,
-                    270 => Rotation::Left,
-                    _ => panic!(),
-                };
-                Ok(Rotate(rotation))
+            'L' | 'R' => {
+                let rotation: Rotation = s.parse()?;
+                // the ship and waypoint only ever turn in quarter turns
+                match rotation.quarter_turns() {
+                    Some(_) => Ok(Rotate(rotation)),
+                    None => Err(ParseError::expected("a multiple of 90 degrees")
+                        .with_text(rest)
+                        .at_columns(first.len_utf8()..s.len())),
+                }
             }
             'N' => Ok(Move(Vector2::new(0, amount))),
             'S' => Ok(Move(Vector2::new(0, -amount))),
             'E' => Ok(Move(Vector2::new(amount, 0))),
             'W' => Ok(Move(Vector2::new(-amount, 0))),
-            _ => panic!(),
+            _ => Err(ParseError::expected("one of N, S, E, W, L, R or F")
+                .with_text(first.to_string())
+                .at_columns(0..1)),
         }
     }
 }
 
 #[aoc_generator(day12)]
-fn parse_input(input: &str) -> Vec<Action> {
-    input
-        .lines()
-        .filter_map(|l| Action::try_from(l).ok())
-        .collect()
+fn parse_input(input: &str) -> Result<Vec<Action>, ParseError> {
+    ParseMode::current().parse_lines_with(input, |l| Action::try_from(l))
 }
 
 #[aoc(day12, part1)]
-fn part1(input: &Vec<Action>) -> i32 {
+fn part1(input: &[Action]) -> i32 {
     let mut ship = Vector2::zero();
     let mut direction = Direction::Right;
     for action in input {
         match action {
             Action::Move(vec) => ship += *vec,
-            Action::Rotate(dir) => direction = direction.rotated(*dir),
+            Action::Rotate(rotation) => {
+                direction = direction
+                    .rotated(*rotation)
+                    .expect("rotations are validated when parsing")
+            }
             Action::MoveForward(amount) => ship += direction.vector().scaled(*amount),
         }
     }
@@ -100,19 +82,40 @@ fn part1(input: &Vec<Action>) -> i32 {
 }
 
 #[aoc(day12, part2)]
-fn part2(input: &Vec<Action>) -> i32 {
+fn part2(input: &[Action]) -> i32 {
     let mut ship = Vector2::zero();
     let mut waypoint = Vector2::new(10, 1);
     for action in input {
         match action {
             Action::Move(vec) => waypoint += *vec,
-            Action::Rotate(dir) => match dir {
-                Rotation::Reverse => waypoint = -waypoint,
-                Rotation::Left => waypoint = waypoint.rotate_left_about_origin(),
-                Rotation::Right => waypoint = waypoint.rotate_right_about_origin(),
-            },
+            Action::Rotate(rotation) => {
+                waypoint = waypoint
+                    .rotated(*rotation)
+                    .expect("rotations are validated when parsing")
+            }
             Action::MoveForward(amount) => ship += waypoint.scaled(*amount),
         }
     }
     ship.man_distance_to_origin()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "F10\nN3\nF7\nR90\nF11";
+
+    #[test]
+    fn example() {
+        let actions = parse_input(EXAMPLE).unwrap();
+        assert_eq!(part1(&actions), 25);
+        assert_eq!(part2(&actions), 286);
+    }
+
+    #[test]
+    fn rejects_odd_angles() {
+        let err = parse_input("F10\nL45").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.columns(), Some(1..3));
+    }
+}
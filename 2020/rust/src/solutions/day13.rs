@@ -1,74 +1,87 @@
 // --- Day 13: Shuttle Search ---
 
-use std::convert::From;
+use crate::common::parse_error::ParseError;
+use crate::common::schedule::Schedule;
+use std::str::FromStr;
 
 #[derive(Debug)]
 struct Timetable {
     earliest: u64,
-    /// Out of service 'x' -> None
-    buses: Vec<Option<u64>>,
+    /// Out of service 'x's are gaps in the schedule.
+    schedule: Schedule,
 }
 
-impl From<&str> for Timetable {
-    fn from(s: &str) -> Self {
-        let mut lines = s.lines().into_iter();
-        let earliest: u64 = lines.next().unwrap().parse().unwrap();
-        let buses: Vec<Option<u64>> = lines
+impl FromStr for Timetable {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let earliest: u64 = lines
             .next()
-            .unwrap()
-            .split(",")
-            .into_iter()
-            .map(|b| b.parse().ok())
-            .collect();
-        Self { earliest, buses }
-    }
-}
-
-impl Timetable {
-    fn real_buses(&self) -> Vec<u64> {
-        self.buses.clone().into_iter().filter_map(|e| e).collect()
+            .ok_or_else(|| ParseError::expected("the earliest departure time"))?
+            .parse()
+            .map_err(|e| ParseError::from(e).at_line(1))?;
+        let schedule = lines
+            .next()
+            .ok_or_else(|| ParseError::expected("a list of buses").at_line(2))?;
+        let mut buses = vec![];
+        let mut offset = 0;
+        for bus in schedule.split(',') {
+            let error = || {
+                ParseError::expected("a bus number or 'x'")
+                    .with_text(bus)
+                    .at_columns(offset..offset + bus.len())
+                    .at_line(2)
+            };
+            let parsed = match bus {
+                "x" => None,
+                // a bus 0 would never leave
+                _ => match bus.parse() {
+                    Ok(0) => return Err(error()),
+                    Ok(number) => Some(number),
+                    Err(e) => return Err(error().with_source(e)),
+                },
+            };
+            buses.push(parsed);
+            offset += bus.len() + 1;
+        }
+        let schedule = Schedule::new(buses).expect("bus 0 was rejected");
+        Ok(Self { earliest, schedule })
     }
 }
 
 #[aoc_generator(day13)]
-fn parse_input(input: &str) -> Timetable {
-    Timetable::from(input)
+fn parse_input(input: &str) -> Result<Timetable, ParseError> {
+    input.parse()
 }
 
 #[aoc(day13, part1)]
 fn part1(input: &Timetable) -> Option<u64> {
-    let real_buses = input.real_buses();
-    for i in input.earliest.. {
-        for bus in real_buses.iter() {
-            if i % bus == 0 {
-                let wait_mins = i - input.earliest;
-                return Some(wait_mins * bus);
-            }
-        }
-    }
-    None
+    let first = input.schedule.first_departure(input.earliest)?;
+    Some((first.time - input.earliest) * first.bus)
 }
 
 #[aoc(day13, part2)]
-fn part2(input: &Timetable) -> u64 {
-    // can't take credit for figuring this out -> see reddit
-    // I originally used chinese remainder theorem, but this is much cleaner
-    //
-    // step in increments of the first bus until the second is 'in sync'
-    // then step in increments of both (multiplied) until the third is 'in sync', and
-    // we reach the result in a few hundred iterations!
-    let mut factor = 1;
-    let mut bus_num = 0;
-    let mut time = 0;
-    for bus in input.buses.iter() {
-        bus_num += 1;
-        if let Some(bus) = bus {
-            while (time + bus_num) % bus != 0 {
-                time += factor;
-            }
-            factor *= bus;
-        }
+fn part2(input: &Timetable) -> Option<u64> {
+    // bus n leaves n minutes after t
+    input.schedule.earliest_alignment()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let timetable = parse_input("939\n7,13,x,x,59,x,31,19").unwrap();
+        assert_eq!(part1(&timetable), Some(295));
+        assert_eq!(part2(&timetable), Some(1068781));
+        let timetable = parse_input("0\n17,x,13,19").unwrap();
+        assert_eq!(part2(&timetable), Some(3417));
     }
 
-    time + 1
+    #[test]
+    fn bus_zero_is_rejected() {
+        let err = parse_input("939\n7,0").unwrap_err();
+        assert_eq!(err.columns(), Some(2..3));
+    }
 }
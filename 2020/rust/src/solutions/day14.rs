@@ -1,10 +1,13 @@
 // --- Day 14: Docking Data ---
 // implemented using bitwise operations alone, this is a fast implementation
 
+use crate::common::parse_error::{parse_match, ParseError};
+use crate::common::parse_mode::parse_lines;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashMap;
-use std::convert::From;
+use std::error::Error;
+use std::fmt::{self, Write};
 use std::str::FromStr;
 
 /// 1 = presence of a masking bit
@@ -38,10 +41,17 @@ impl Mask {
     #[inline]
     fn apply_one_zero(&self, num: u64) -> u64 {
         let num = self.apply_ones(num);
-        let num = self.apply_zeros(num);
-        num
+
+        self.apply_zeros(num)
+    }
+    /// The part 2 addresses, as a pattern rather than every one of them.
+    fn address_pattern(&self, addr: u64) -> AddressPattern {
+        AddressPattern::new(self.apply_ones(addr), self.floating)
     }
     /// Part 2 addresses.
+    /// There are 2^n of them for n floating bits, so prefer `address_pattern`.
+    /// Panics for 32 or more floating bits.
+    #[allow(dead_code)]
     fn addresses(&self, addr: u64) -> Vec<u64> {
         let addr = self.apply_ones(addr); // add 1 bits in, leaving zeros untouched
         let addr = addr & !self.floating; // zero-out floating bits in the input
@@ -72,86 +82,311 @@ impl Mask {
     }
 }
 
-impl From<&str> for Mask {
-    fn from(s: &str) -> Self {
+/// A set of addresses, each floating bit can be either 0 or 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AddressPattern {
+    /// The bits that aren't floating, floating bits are always 0 here.
+    fixed: u64,
+    floating: u64,
+}
+
+impl AddressPattern {
+    fn new(address: u64, floating: u64) -> Self {
+        AddressPattern {
+            fixed: address & !floating,
+            floating,
+        }
+    }
+
+    /// How many addresses match, `u128` as there are 2^64 when every bit floats.
+    fn len(&self) -> u128 {
+        1 << self.floating.count_ones()
+    }
+
+    fn overlaps(&self, other: &AddressPattern) -> bool {
+        let both_fixed = !(self.floating | other.floating);
+        (self.fixed ^ other.fixed) & both_fixed == 0
+    }
+
+    /// The addresses matched by `self` but not `other`, as non-overlapping patterns.
+    fn subtract(&self, other: &AddressPattern) -> Vec<AddressPattern> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        // fix the floating bits that `other` doesn't float one at a time,
+        // splitting off the half that doesn't match `other` each time
+        let mut pieces = vec![];
+        let mut rest = *self;
+        let mut splits = self.floating & !other.floating;
+        while splits != 0 {
+            let bit = splits & splits.wrapping_neg();
+            splits &= !bit;
+            rest.floating &= !bit;
+            pieces.push(AddressPattern {
+                fixed: rest.fixed | (!other.fixed & bit),
+                floating: rest.floating,
+            });
+            rest.fixed |= other.fixed & bit;
+        }
+        // what's left is entirely within `other`
+        pieces
+    }
+}
+
+/// Memory for the version 2 decoder, which writes to many addresses at once.
+///
+/// Writes are stored as patterns. Each write removes its addresses from the
+/// earlier patterns, so they never overlap, and the sum doesn't have to visit
+/// every address.
+#[derive(Debug, Clone, Default)]
+struct FloatingMemory {
+    writes: Vec<(AddressPattern, u64)>,
+}
+
+impl FloatingMemory {
+    fn write(&mut self, pattern: AddressPattern, value: u64) {
+        self.writes = self
+            .writes
+            .iter()
+            .flat_map(|(earlier, v)| earlier.subtract(&pattern).into_iter().map(move |p| (p, *v)))
+            .collect();
+        self.writes.push((pattern, value));
+    }
+
+    fn sum(&self) -> u128 {
+        self.writes
+            .iter()
+            .map(|(pattern, value)| pattern.len() * *value as u128)
+            .sum()
+    }
+}
+
+impl FromStr for Mask {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.len() > u64::BITS as usize {
+            return Err(ParseError::expected("a mask of 1 to 64 bits")
+                .with_text(s)
+                .at_columns(0..s.len()));
+        }
         let mut zeros = 0;
         let mut ones = 0;
         let mut floating = 0;
         let mut value = 1;
-        for digit in s.chars().into_iter().rev() {
+        for (i, digit) in s.char_indices().rev() {
             match digit {
                 '0' => zeros += value,
                 '1' => ones += value,
-                _ => floating += value,
+                'X' => floating += value,
+                _ => {
+                    return Err(ParseError::expected("0, 1 or X")
+                        .with_text(digit.to_string())
+                        .at_columns(i..i + digit.len_utf8()))
+                }
             }
             value <<= 1;
         }
-        Self {
+        Ok(Self {
             ones,
             zeros,
             floating,
-        }
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
     #[test]
     fn test_mask_gen_ones() {
         let mask = "0XX1XX1";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         assert_eq!(mask.ones, 0b0001001);
     }
     #[test]
     fn test_mask_gen_zeros() {
         let mask = "0XX1XX1";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         assert_eq!(mask.zeros, 0b1000000);
     }
     #[test]
     fn test_mask_apply() {
         let mask = "0XX1XX1";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         assert_eq!(mask.apply_one_zero(0b0000000), 0b0001001);
     }
     #[test]
     fn floating_mask_small() {
         let mask = "XXXX";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         assert_eq!(mask.floating, 0b1111);
     }
     #[test]
     fn floating_mask_mixed() {
         let mask = "1111XXXX0000";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         assert_eq!(mask.floating, 0b000011110000);
     }
     #[test]
     fn test_addresses_only_floating_2() {
         let mask = "XX";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         let addrs = mask.addresses(0b11);
         assert_eq!(addrs, vec![0, 1, 2, 3]);
     }
     #[test]
     fn test_addresses_only_floating_3() {
         let mask = "XXX";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         let addrs = mask.addresses(0b111);
         assert_eq!(addrs, vec![0, 1, 2, 3, 4, 5, 6, 7]);
     }
     #[test]
     fn test_addresses_mixed() {
         let mask = "11XX";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         let addrs = mask.addresses(0b0000);
         assert_eq!(addrs, vec![0b1100, 0b1101, 0b1110, 0b1111]);
     }
+    #[test]
+    fn subtract_patterns() {
+        // 1X0X minus X10X leaves 100X
+        let a = AddressPattern::new(0b1000, 0b0101);
+        let b = AddressPattern::new(0b0100, 0b1001);
+        assert_eq!(a.subtract(&b), vec![AddressPattern::new(0b1000, 0b0001)]);
+        assert_eq!(a.subtract(&a), vec![]);
+        let c = AddressPattern::new(0b0010, 0);
+        assert_eq!(a.subtract(&c), vec![a]);
+    }
+
+    /// A mask of `width` bits, with at most `max_floating` floating.
+    fn mask_strategy(width: usize, max_floating: usize) -> impl Strategy<Value = String> {
+        proptest::collection::vec(prop_oneof!["0", "1", "X"], width)
+            .prop_filter("too many floating bits", move |bits| {
+                bits.iter().filter(|b| *b == "X").count() <= max_floating
+            })
+            .prop_map(|bits| bits.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn subtraction_matches_the_addresses(
+            a in mask_strategy(8, 8),
+            b in mask_strategy(8, 8),
+            addr_a in 0u64..256,
+            addr_b in 0u64..256,
+        ) {
+            let (a, b) = (a.parse::<Mask>().unwrap(), b.parse::<Mask>().unwrap());
+            let (pa, pb) = (a.address_pattern(addr_a), b.address_pattern(addr_b));
+            let expected: HashSet<u64> = a.addresses(addr_a)
+                .into_iter()
+                .filter(|addr| !b.addresses(addr_b).contains(addr))
+                .collect();
+            let mut found = HashSet::new();
+            for piece in pa.subtract(&pb) {
+                for addr in Mask::new(0, 0, piece.floating).addresses(piece.fixed) {
+                    // pieces never overlap
+                    prop_assert!(found.insert(addr));
+                }
+            }
+            prop_assert_eq!(found, expected);
+        }
+
+        #[test]
+        fn floating_memory_matches_brute_force(
+            writes in proptest::collection::vec(
+                (mask_strategy(12, 6), 0u64..4096, 0u64..1000),
+                1..12,
+            )
+        ) {
+            let mut brute_force = HashMap::new();
+            let mut memory = FloatingMemory::default();
+            for (mask, addr, value) in writes {
+                let mask: Mask = mask.parse().unwrap();
+                for a in mask.addresses(addr) {
+                    brute_force.insert(a, value);
+                }
+                memory.write(mask.address_pattern(addr), value);
+            }
+            let expected: u64 = brute_force.values().sum();
+            prop_assert_eq!(memory.sum(), expected as u128);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_masks() {
+        let err = parse_input("mask = 10X\nmask = 1X2X").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.columns(), Some(9..10));
+        assert_eq!(err.text(), Some("2"));
+        let too_wide = format!("mask = {}", "X".repeat(65));
+        assert_eq!(parse_input(&too_wide).unwrap_err().columns(), Some(7..72));
+        assert!(parse_input("mask = ").is_err());
+        assert!(parse_input("mem[1]").is_err());
+    }
+
+    #[test]
+    fn traces() {
+        let program = parse_input("mask = X1X0\nmem[1] = 3\nmask = 0X\nmem[2] = 5").unwrap();
+        let v1 = trace(&program, Decoder::V1).unwrap();
+        assert_eq!(
+            v1,
+            vec![
+                StoredValue {
+                    instruction: 1,
+                    requested: 1,
+                    address: 1,
+                    value: 0b0110
+                },
+                StoredValue {
+                    instruction: 3,
+                    requested: 2,
+                    address: 2,
+                    value: 5
+                },
+            ]
+        );
+        let v2 = trace(&program, Decoder::V2).unwrap();
+        let addresses: Vec<_> = v2.iter().map(|w| w.address).collect();
+        assert_eq!(addresses, vec![0b0101, 0b0111, 0b1101, 0b1111, 2, 3]);
+        assert_eq!(
+            trace_csv(&v2[4..]),
+            "instruction,requested,address,value\n3,2,2,5\n3,2,3,5\n"
+        );
+    }
+
+    #[test]
+    fn traces_need_few_floating_bits() {
+        let mask = format!("mask = {}", "X".repeat(36));
+        let program = parse_input(&format!("mem[0] = 1\n{}\nmem[7] = 1", mask)).unwrap();
+        assert_eq!(
+            trace(&program, Decoder::V2),
+            Err(TooManyFloatingBits {
+                instruction: 2,
+                floating: 36
+            })
+        );
+        // only the addresses float
+        assert_eq!(trace(&program, Decoder::V1).unwrap().len(), 2);
+        let mask = format!("mask = {}{}", "0".repeat(20), "X".repeat(16));
+        let program = parse_input(&format!("{}\nmem[7] = 1", mask)).unwrap();
+        assert_eq!(trace(&program, Decoder::V2).unwrap().len(), 1 << 16);
+    }
+
+    #[test]
+    fn every_bit_floating() {
+        let mut memory = FloatingMemory::default();
+        memory.write(AddressPattern::new(0, u64::MAX), 2);
+        memory.write(AddressPattern::new(0, u64::MAX - 1), 1);
+        assert_eq!(memory.sum(), 3 << 63);
+    }
+
     #[test]
     fn addresses_real_example() {
         let mask = "X1001X";
-        let mask = Mask::from(mask);
+        let mask: Mask = mask.parse().unwrap();
         let addrs = mask.addresses(0b101010);
         assert_eq!(addrs, vec![0b011010, 0b011011, 0b111010, 0b111011]);
     }
@@ -164,32 +399,140 @@ enum Action {
     SetMemory(u64, u64),
 }
 
-impl From<&str> for Action {
-    fn from(s: &str) -> Self {
+impl FromStr for Action {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
             static ref MEMORY_REGEX: Regex = Regex::from_str(r"^mem\[(\d+)\]\s=\s(\d+)$").unwrap();
             static ref MASK_REGEX: Regex = Regex::from_str(r"^mask\s=\s(.+)$").unwrap();
         }
-        if MEMORY_REGEX.is_match(s) {
-            let caps = MEMORY_REGEX.captures(s).unwrap();
-            let addr: u64 = caps.get(1).unwrap().as_str().parse().unwrap();
-            let val: u64 = caps.get(2).unwrap().as_str().parse().unwrap();
-            Action::SetMemory(addr, val)
+        if let Some(caps) = MEMORY_REGEX.captures(s) {
+            let addr: u64 = parse_match(caps.get(1).unwrap())?;
+            let val: u64 = parse_match(caps.get(2).unwrap())?;
+            Ok(Action::SetMemory(addr, val))
+        } else if let Some(caps) = MASK_REGEX.captures(s) {
+            let mask = caps.get(1).unwrap();
+            let parsed = mask
+                .as_str()
+                .parse()
+                .map_err(|e: ParseError| e.at_columns(mask.start()..mask.end()))?;
+            Ok(Action::SetMask(parsed))
         } else {
-            let caps = MASK_REGEX.captures(s).unwrap();
-            let mask = Mask::from(caps.get(1).unwrap().as_str());
-            Action::SetMask(mask)
+            Err(ParseError::expected("\"mem[<addr>] = <value>\" or \"mask = <mask>\"").with_text(s))
         }
     }
 }
 
 #[aoc_generator(day14)]
-fn parse_input(input: &str) -> Vec<Action> {
-    input.lines().map(|l| Action::from(l)).collect()
+fn parse_input(input: &str) -> Result<Vec<Action>, ParseError> {
+    parse_lines(input)
+}
+
+/// The version of the decoder chip, see `trace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+enum Decoder {
+    /// The mask applies to values (part 1).
+    V1,
+    /// The mask applies to addresses, with floating bits (part 2).
+    V2,
+}
+
+/// A single value stored to memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StoredValue {
+    /// Which instruction of the program did the write, counting from 0.
+    instruction: usize,
+    /// The address as it was written in the program.
+    requested: u64,
+    /// Where the value actually went.
+    address: u64,
+    value: u64,
+}
+
+/// The most floating bits a version 2 write can have in a trace,
+/// which lists a value for every one of the 2^n addresses.
+const TRACE_FLOATING_LIMIT: u32 = 16;
+
+/// A version 2 write had too many floating addresses to trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TooManyFloatingBits {
+    instruction: usize,
+    floating: u32,
+}
+
+impl fmt::Display for TooManyFloatingBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "instruction {} writes to 2^{} addresses, at most 2^{} can be traced",
+            self.instruction, self.floating, TRACE_FLOATING_LIMIT
+        )
+    }
+}
+
+impl Error for TooManyFloatingBits {}
+
+/// Every value stored by the program, in order.
+///
+/// Version 2 writes are listed for every floating address, so masks with more
+/// than `TRACE_FLOATING_LIMIT` `X`s are an error.
+#[allow(dead_code)]
+fn trace(program: &[Action], decoder: Decoder) -> Result<Vec<StoredValue>, TooManyFloatingBits> {
+    let mut mask = &Mask::blank();
+    let mut writes = vec![];
+    for (instruction, act) in program.iter().enumerate() {
+        let (requested, value) = match act {
+            Action::SetMask(msk) => {
+                mask = msk;
+                continue;
+            }
+            Action::SetMemory(key, value) => (*key, *value),
+        };
+        let write = |address, value| StoredValue {
+            instruction,
+            requested,
+            address,
+            value,
+        };
+        match decoder {
+            Decoder::V1 => writes.push(write(requested, mask.apply_one_zero(value))),
+            Decoder::V2 => {
+                let floating = mask.floating.count_ones();
+                if floating > TRACE_FLOATING_LIMIT {
+                    return Err(TooManyFloatingBits {
+                        instruction,
+                        floating,
+                    });
+                }
+                writes.extend(
+                    mask.addresses(requested)
+                        .into_iter()
+                        .map(|address| write(address, value)),
+                )
+            }
+        }
+    }
+    Ok(writes)
+}
+
+/// The trace as CSV, with a header row.
+#[allow(dead_code)]
+fn trace_csv(writes: &[StoredValue]) -> String {
+    let mut csv = String::from("instruction,requested,address,value\n");
+    for w in writes {
+        writeln!(
+            csv,
+            "{},{},{},{}",
+            w.instruction, w.requested, w.address, w.value
+        )
+        .unwrap();
+    }
+    csv
 }
 
 #[aoc(day14, part1)]
-fn part1(input: &Vec<Action>) -> u64 {
+fn part1(input: &[Action]) -> u64 {
     let mut mask = &Mask::blank();
     let mut mem = HashMap::with_capacity(input.len());
     for act in input {
@@ -205,19 +548,14 @@ fn part1(input: &Vec<Action>) -> u64 {
 }
 
 #[aoc(day14, part2)]
-fn part2(input: &Vec<Action>) -> u64 {
+fn part2(input: &[Action]) -> u128 {
     let mut mask = &Mask::blank();
-    let mut mem = HashMap::with_capacity(input.len());
+    let mut mem = FloatingMemory::default();
     for act in input {
         match act {
             Action::SetMask(msk) => mask = msk,
-            Action::SetMemory(key, val) => {
-                let addrs = mask.addresses(*key);
-                for addr in addrs {
-                    mem.insert(addr, *val);
-                }
-            }
+            Action::SetMemory(key, val) => mem.write(mask.address_pattern(*key), *val),
         }
     }
-    mem.values().sum()
+    mem.sum()
 }
@@ -1,43 +1,194 @@
 // --- Day 15: Rambunctious Recitation ---
-//
+// The game is the Van Eck sequence with a different start. Every number after
+// the starting ones is the gap between the last two times a number was spoken,
+// so it's smaller than the turn count. Most of them are small, so those go in a
+// dense Vec and the rare big ones in a HashMap.
+
+use crate::common::parse_error::ParseError;
 use std::collections::HashMap;
 
 #[aoc_generator(day15)]
-fn parse_input(input: &str) -> Vec<u32> {
-    input.split(",").map(|line| line.parse().unwrap()).collect()
+fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    let mut offset = 0;
+    let mut nums = vec![];
+    for num in input.trim().split(',') {
+        nums.push(num.parse().map_err(|e| {
+            ParseError::from(e)
+                .with_text(num)
+                .at_columns(offset..offset + num.len())
+        })?);
+        offset += num.len() + 1;
+    }
+    Ok(nums)
 }
 
-#[aoc(day15, part1)]
-fn part1(input: &Vec<u32>) -> u32 {
-    get_number(input, 2020)
+/// Numbers below this are tracked in a `Vec` if the game doesn't say how long it will be.
+const DEFAULT_DENSE_LIMIT: usize = 1 << 16;
+
+/// The most numbers tracked in a `Vec` however long the game is. A table for
+/// every number in part 2 would take 120 MB, this one takes 4 MB and is faster
+/// as it stays in the cache.
+const MAX_DENSE_LIMIT: usize = 1 << 20;
+
+/// The dense limit for a game of `turns` turns, which never speaks a number
+/// of `turns` or more other than the starting numbers.
+fn dense_limit(turns: u32) -> usize {
+    (turns as usize).min(MAX_DENSE_LIMIT)
 }
 
-#[aoc(day15, part2)]
-fn part2(input: &Vec<u32>) -> u32 {
-    get_number(input, 30_000_000)
+/// Plays the memory game, yielding each number as it's spoken.
+#[derive(Debug, Clone)]
+struct MemoryGame {
+    starting: Vec<u32>,
+    /// The turn each number below `dense.len()` was last spoken, 0 for never.
+    dense: Vec<u32>,
+    /// Same as `dense`, for the numbers too big for it.
+    sparse: HashMap<u32, u32>,
+    /// How many turns have been played.
+    turn: u32,
+    /// The number spoken on the last turn, which isn't recorded until the next.
+    last: u32,
+}
+
+#[allow(dead_code)]
+impl MemoryGame {
+    fn new(starting: &[u32]) -> Self {
+        Self::with_dense_limit(starting, DEFAULT_DENSE_LIMIT)
+    }
+
+    /// A game that keeps numbers below `limit` in a `Vec`, which takes 4 bytes
+    /// for each.
+    fn with_dense_limit(starting: &[u32], limit: usize) -> Self {
+        MemoryGame {
+            starting: starting.to_vec(),
+            dense: vec![0; limit],
+            sparse: HashMap::new(),
+            turn: 0,
+            last: 0,
+        }
+    }
+
+    /// The number of turns played so far.
+    fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// The last turn `number` was spoken, before the current one.
+    fn last_seen(&self, number: u32) -> Option<u32> {
+        let turn = match self.dense.get(number as usize) {
+            Some(turn) => *turn,
+            None => self.sparse.get(&number).copied().unwrap_or(0),
+        };
+        Some(turn).filter(|t| *t != 0)
+    }
+
+    /// Records that `number` was spoken on `turn`, returning when it was
+    /// spoken before that, 0 for never. The lookup and update are done together
+    /// as the memory access is what takes the time.
+    fn replace_seen(&mut self, number: u32, turn: u32) -> u32 {
+        match self.dense.get_mut(number as usize) {
+            Some(seen) => std::mem::replace(seen, turn),
+            None => self.sparse.insert(number, turn).unwrap_or(0),
+        }
+    }
+}
+
+impl Iterator for MemoryGame {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.starting.is_empty() {
+            return None;
+        }
+        let seen = match self.turn {
+            0 => 0,
+            turn => self.replace_seen(self.last, turn),
+        };
+        let number = match self.starting.get(self.turn as usize) {
+            Some(number) => *number,
+            None if seen == 0 => 0,
+            None => self.turn - seen,
+        };
+        self.turn += 1;
+        self.last = number;
+        Some(number)
+    }
 }
 
-fn get_number(input: &Vec<u32>, turn_number: u32) -> u32 {
-    // number -> turn it was last spoken
-    let mut map = HashMap::new();
-    let mut turn = 0;
-    let mut num = 0;
-    for val in input.iter() {
-        turn += 1;
-        num = *val;
-        map.insert(num, turn);
-    }
-    while turn < turn_number {
-        let prev_num = num;
-        let prev_turn = turn;
-        turn += 1;
-        num = match map.get(&prev_num) {
-            Some(last_seen_turn) => prev_turn - last_seen_turn,
-            None => 0,
+/// The number spoken on `turn`, counting from 1.
+fn nth_spoken(starting: &[u32], turn: u32) -> Option<u32> {
+    let turn = turn.checked_sub(1)?;
+    MemoryGame::with_dense_limit(starting, dense_limit(turn + 1)).nth(turn as usize)
+}
+
+/// The first turn that `number` is spoken, looking at up to `max_turns` turns.
+#[allow(dead_code)]
+fn first_spoken(starting: &[u32], number: u32, max_turns: u32) -> Option<u32> {
+    MemoryGame::with_dense_limit(starting, dense_limit(max_turns))
+        .take(max_turns as usize)
+        .position(|n| n == number)
+        .map(|turn| turn as u32 + 1)
+}
+
+/// How often numbers were repeated, and how far apart, see `gap_statistics`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GapStatistics {
+    turns: u32,
+    /// Turns where the last number hadn't been spoken before, so 0 was spoken.
+    new_numbers: u32,
+    /// The largest gap between a number being spoken twice, and that number.
+    longest: Option<(u32, u32)>,
+    /// The average gap of the repeated numbers.
+    mean: f64,
+}
+
+/// Statistics about the gaps (the numbers spoken after the starting numbers)
+/// over the first `turns` turns.
+#[allow(dead_code)]
+fn gap_statistics(starting: &[u32], turns: u32) -> GapStatistics {
+    let mut game = MemoryGame::with_dense_limit(starting, dense_limit(turns));
+    let mut new_numbers = 0;
+    let mut longest: Option<(u32, u32)> = None;
+    let (mut repeats, mut total) = (0u32, 0u64);
+    game.by_ref()
+        .take(starting.len().min(turns as usize))
+        .for_each(drop);
+    while game.turn() < turns {
+        let previous = game.last;
+        let gap = match game.next() {
+            Some(gap) => gap,
+            None => break,
         };
-        map.insert(prev_num, prev_turn);
+        if gap == 0 {
+            new_numbers += 1;
+            continue;
+        }
+        repeats += 1;
+        total += gap as u64;
+        if longest.is_none_or(|(_, g)| gap > g) {
+            longest = Some((previous, gap));
+        }
     }
-    num
+    GapStatistics {
+        turns: game.turn(),
+        new_numbers,
+        longest,
+        mean: if repeats == 0 {
+            0.0
+        } else {
+            total as f64 / repeats as f64
+        },
+    }
+}
+
+#[aoc(day15, part1)]
+fn part1(input: &[u32]) -> Option<u32> {
+    nth_spoken(input, 2020)
+}
+
+#[aoc(day15, part2)]
+fn part2(input: &[u32]) -> Option<u32> {
+    nth_spoken(input, 30_000_000)
 }
 
 #[cfg(test)]
@@ -46,19 +197,73 @@ mod tests {
 
     #[test]
     fn test_nums() {
-        let result = part1(&vec![1, 3, 2]);
-        assert_eq!(result, 1);
+        let result = part1(&[1, 3, 2]);
+        assert_eq!(result, Some(1));
     }
 
     #[test]
     fn test_nums_2() {
-        let result = part1(&vec![2, 1, 3]);
-        assert_eq!(result, 10);
+        let result = part1(&[2, 1, 3]);
+        assert_eq!(result, Some(10));
     }
 
     #[test]
     fn test_nums_3() {
-        let result = part1(&vec![1, 2, 3]);
-        assert_eq!(result, 27);
+        let result = part1(&[1, 2, 3]);
+        assert_eq!(result, Some(27));
+    }
+
+    #[test]
+    fn spoken_sequence() {
+        let spoken: Vec<u32> = MemoryGame::new(&[0, 3, 6]).take(10).collect();
+        assert_eq!(spoken, vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]);
+        assert_eq!(nth_spoken(&[0, 3, 6], 2020), Some(436));
+        assert_eq!(nth_spoken(&[0, 3, 6], 0), None);
+        assert_eq!(MemoryGame::new(&[]).next(), None);
+    }
+
+    /// The first `turns` numbers spoken, played the obvious way with a `HashMap`.
+    fn spoken_with_hashmap(starting: &[u32], turns: usize) -> Vec<u32> {
+        let mut spoken = starting.to_vec();
+        let mut last_seen = HashMap::new();
+        for turn in 1..turns {
+            let last = spoken[turn - 1];
+            let seen = last_seen.insert(last, turn);
+            if turn >= starting.len() {
+                spoken.push(seen.map_or(0, |seen| (turn - seen) as u32));
+            }
+        }
+        spoken.truncate(turns);
+        spoken
+    }
+
+    #[test]
+    fn large_numbers_use_the_fallback() {
+        let expected = spoken_with_hashmap(&[7, 3, 6], 2020);
+        assert_eq!(&expected[..10], &[7, 3, 6, 0, 0, 1, 0, 2, 0, 2]);
+        // everything from 4 up goes in the HashMap
+        let hashed: Vec<u32> = MemoryGame::with_dense_limit(&[7, 3, 6], 4)
+            .take(2020)
+            .collect();
+        assert_eq!(hashed, expected);
+        let dense: Vec<u32> = MemoryGame::new(&[7, 3, 6]).take(2020).collect();
+        assert_eq!(dense, expected);
+        let big = MemoryGame::with_dense_limit(&[1_000_000, 1_000_000], 2);
+        assert_eq!(
+            big.take(4).collect::<Vec<_>>(),
+            vec![1_000_000, 1_000_000, 1, 0]
+        );
+    }
+
+    #[test]
+    fn first_times_and_gaps() {
+        assert_eq!(first_spoken(&[0, 3, 6], 4, 10), Some(9));
+        assert_eq!(first_spoken(&[0, 3, 6], 5, 10), None);
+        let stats = gap_statistics(&[0, 3, 6], 10);
+        assert_eq!(stats.turns, 10);
+        // turns 4 to 10 speak 0, 3, 3, 1, 0, 4, 0
+        assert_eq!(stats.new_numbers, 3);
+        assert_eq!(stats.longest, Some((0, 4)));
+        assert!((stats.mean - 11.0 / 4.0).abs() < 1e-9);
     }
 }
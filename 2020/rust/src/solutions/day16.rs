@@ -1,135 +1,175 @@
 // --- Day 16: Ticket Translation ---
+use crate::common::assignment::{assign, AssignmentError};
+use crate::common::interval::IntervalSet;
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
 use std::collections::{HashMap, HashSet};
-use std::convert::{From, TryInto};
 use std::ops::RangeInclusive;
+use std::str::FromStr;
 
-const FIELD_COUNT: usize = 20;
+/// The fields whose values make up the part 2 answer start with this.
+const DEPARTURE_PREFIX: &str = "departure";
 
 #[derive(Debug, Clone)]
 struct Field {
     name: String,
-    rule1: RangeInclusive<u16>,
-    rule2: RangeInclusive<u16>,
+    /// The values that are valid for this field.
+    ranges: IntervalSet<u16>,
 }
 
 impl Field {
     fn matches_value(&self, value: &u16) -> bool {
-        self.rule1.contains(&value) || self.rule2.contains(&value)
+        self.ranges.contains(value)
     }
 }
 
 impl Field {
     /// Parsing helper for getting range from string range like '123-456'
-    fn parse_range(s: &str) -> RangeInclusive<u16> {
-        let mut range = s.split("-").map(|n| n.parse::<u16>().unwrap()).into_iter();
-        RangeInclusive::new(range.next().unwrap(), range.next().unwrap())
+    fn parse_range(s: &str) -> Result<RangeInclusive<u16>, ParseError> {
+        let mut range = s.split('-');
+        match (range.next(), range.next(), range.next()) {
+            (Some(start), Some(end), None) => {
+                let (start, end): (u16, u16) = (start.parse()?, end.parse()?);
+                if start > end {
+                    return Err(ParseError::expected("<min> no bigger than <max>").with_text(s));
+                }
+                Ok(start..=end)
+            }
+            _ => Err(ParseError::expected("\"<min>-<max>\"").with_text(s)),
+        }
     }
 }
 
-impl From<&str> for Field {
+impl FromStr for Field {
+    type Err = ParseError;
     // departure location: 28-184 or 203-952
-    fn from(s: &str) -> Self {
-        let mut parts = s.split(": ").into_iter();
-        let name = parts.next().unwrap().to_string();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let shape =
+            || ParseError::expected("\"<name>: <min>-<max> or <min>-<max> ...\"").with_text(s);
+        let mut parts = s.split(": ");
+        let name = parts.next().ok_or_else(shape)?.to_string();
         let ranges = parts
             .next()
-            .unwrap()
+            .ok_or_else(shape)?
             .split(" or ")
-            .into_iter()
-            .map(|s| Field::parse_range(s))
-            .collect::<Vec<_>>();
-        Self {
-            name,
-            rule1: ranges[0].clone(),
-            rule2: ranges[1].clone(),
+            .map(Field::parse_range)
+            .collect::<Result<_, _>>()?;
+        if parts.next().is_some() {
+            return Err(shape());
         }
+        Ok(Self { name, ranges })
     }
 }
 
 #[derive(Debug, Clone)]
 struct Ticket {
-    values: [u16; FIELD_COUNT],
+    values: Vec<u16>,
 }
 
-impl From<&str> for Ticket {
-    fn from(s: &str) -> Self {
-        let nums: Vec<_> = s.split(",").map(|n| n.parse::<u16>().unwrap()).collect();
-        let values: [_; FIELD_COUNT] = nums.as_slice().try_into().unwrap();
-        Self { values }
+impl FromStr for Ticket {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|n| n.parse::<u16>())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { values })
     }
 }
 
 #[derive(Debug)]
 struct Manifest {
-    fields: [Field; FIELD_COUNT],
+    fields: Vec<Field>,
     my_ticket: Ticket,
     other_tickets: Vec<Ticket>,
+    /// The values that are valid for at least one field.
+    valid_values: IntervalSet<u16>,
+}
+
+/// Consumes the next line, which must be exactly `text`.
+fn expect_line<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    text: &str,
+) -> Result<(), ParseError> {
+    match lines.next() {
+        Some((_, line)) if line == text => Ok(()),
+        Some((i, line)) => Err(ParseError::expected(format!("{:?}", text))
+            .with_text(line)
+            .at_line(i)),
+        None => Err(ParseError::expected(format!("{:?}", text))),
+    }
 }
 
-impl From<&str> for Manifest {
-    fn from(s: &str) -> Self {
-        let mut lines = s.lines();
-        let fields: [Field; FIELD_COUNT] = (0..FIELD_COUNT)
-            .map(|_| lines.next().unwrap())
-            .map(|l| Field::from(l))
-            .collect::<Vec<_>>()
-            .try_into()
-            .ok()
-            .unwrap();
-        lines.next();
-        lines.next();
-        let my_ticket = lines.next().unwrap();
-        let my_ticket = Ticket::from(my_ticket);
-        lines.next();
-        lines.next();
-        let mut other_tickets = vec![];
-        while let Some(ticket) = lines.next() {
-            let other = Ticket::from(ticket);
-            other_tickets.push(other);
+impl FromStr for Manifest {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(i, l)| (i + 1, l)).peekable();
+        let mut fields: Vec<Field> = vec![];
+        let mut names = HashSet::new();
+        while let Some((i, line)) = lines.next_if(|(_, line)| !line.is_empty()) {
+            let field: Field = line.parse().map_err(|e: ParseError| e.at_line(i))?;
+            if !names.insert(field.name.clone()) {
+                return Err(ParseError::expected("each field to have a different name")
+                    .with_text(line)
+                    .at_line(i));
+            }
+            fields.push(field);
+        }
+        if fields.is_empty() {
+            return Err(ParseError::expected("at least one field").at_line(1));
         }
-        Self {
+        expect_line(&mut lines, "")?;
+        expect_line(&mut lines, "your ticket:")?;
+        // every ticket has a value for each field
+        let parse_ticket = |i: usize, line: &str| -> Result<Ticket, ParseError> {
+            let ticket: Ticket = line.parse().map_err(|e: ParseError| e.at_line(i))?;
+            if ticket.values.len() != fields.len() {
+                return Err(ParseError::expected(format!(
+                    "{} comma separated values, one per field",
+                    fields.len()
+                ))
+                .with_text(line)
+                .at_line(i));
+            }
+            Ok(ticket)
+        };
+        let (i, my_ticket) = lines
+            .next()
+            .ok_or_else(|| ParseError::expected("your ticket"))?;
+        let my_ticket = parse_ticket(i, my_ticket)?;
+        expect_line(&mut lines, "")?;
+        expect_line(&mut lines, "nearby tickets:")?;
+        let other_tickets =
+            ParseMode::current().collect(lines.map(|(i, l)| (i, parse_ticket(i, l))))?;
+        let valid_values = fields
+            .iter()
+            .fold(IntervalSet::new(), |all, f| all.union(&f.ranges));
+        Ok(Self {
             fields,
             my_ticket,
             other_tickets,
-        }
+            valid_values,
+        })
     }
 }
 
 impl Manifest {
     fn is_valid(&self, ticket: &Ticket) -> bool {
-        for v in ticket.values.iter() {
-            let is_valid = self
-                .fields
-                .iter()
-                .map(|f| f.matches_value(&v))
-                .collect::<Vec<_>>()
-                .contains(&true);
-            if !is_valid {
-                return false;
-            }
-        }
-        true
+        ticket.values.iter().all(|v| self.valid_values.contains(v))
     }
     fn invalid_sum(&self, ticket: &Ticket) -> u32 {
-        let mut total = 0;
-        for v in ticket.values.iter() {
-            let is_valid = self
-                .fields
-                .iter()
-                .map(|f| f.matches_value(&v))
-                .collect::<Vec<_>>()
-                .contains(&true);
-            if !is_valid {
-                total += *v as u32;
-            }
-        }
-        total
+        ticket
+            .values
+            .iter()
+            .filter(|v| !self.valid_values.contains(v))
+            .map(|v| *v as u32)
+            .sum()
     }
 }
 
 #[aoc_generator(day16)]
-fn parse_input(input: &str) -> Manifest {
-    Manifest::from(input)
+fn parse_input(input: &str) -> Result<Manifest, ParseError> {
+    input.parse()
 }
 
 #[aoc(day16, part1)]
@@ -141,60 +181,129 @@ fn part1(input: &Manifest) -> u32 {
         .sum()
 }
 
+impl Manifest {
+    /// The ticket column of each field, by name. Every valid ticket's value in
+    /// that column has to match the field, and there has to be only one way to do it.
+    fn field_columns(&self) -> Result<HashMap<String, usize>, AssignmentError> {
+        let tickets: Vec<_> = self
+            .other_tickets
+            .iter()
+            .filter(|t| self.is_valid(t))
+            .collect();
+        let candidates: Vec<Vec<usize>> = self
+            .fields
+            .iter()
+            .map(|field| {
+                (0..self.fields.len())
+                    .filter(|&column| {
+                        tickets
+                            .iter()
+                            .all(|t| field.matches_value(&t.values[column]))
+                    })
+                    .collect()
+            })
+            .collect();
+        let columns = assign(&candidates, self.fields.len())?;
+        Ok(self
+            .fields
+            .iter()
+            .map(|field| field.name.clone())
+            .zip(columns)
+            .collect())
+    }
+
+    /// My ticket's values for the fields whose names start with `prefix`.
+    fn my_values_with_prefix(&self, prefix: &str) -> Result<Vec<u16>, AssignmentError> {
+        let columns = self.field_columns()?;
+        Ok(self
+            .fields
+            .iter()
+            .filter(|field| field.name.starts_with(prefix))
+            .map(|field| self.my_ticket.values[columns[&field.name]])
+            .collect())
+    }
+}
+
 #[aoc(day16, part2)]
-fn part2(input: &Manifest) -> u64 {
-    let mut possible = HashMap::new();
-    for i in 0..FIELD_COUNT {
-        let all_candidates: HashSet<_> = (0..FIELD_COUNT).collect();
-        possible.insert(i, all_candidates);
+fn part2(input: &Manifest) -> Result<u64, AssignmentError> {
+    Ok(input
+        .my_values_with_prefix(DEPARTURE_PREFIX)?
+        .into_iter()
+        .map(u64::from)
+        .product())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "class: 0-1 or 4-19
+row: 0-5 or 8-19
+seat: 0-13 or 16-19
+
+your ticket:
+11,12,13
+
+nearby tickets:
+3,9,18
+15,1,5
+5,14,9";
+
+    #[test]
+    fn small_example() {
+        let manifest = parse_input(EXAMPLE).unwrap();
+        assert_eq!(manifest.fields.len(), 3);
+        let columns = manifest.field_columns().unwrap();
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns["row"], 0);
+        assert_eq!(columns["class"], 1);
+        assert_eq!(columns["seat"], 2);
+        assert_eq!(manifest.my_values_with_prefix("s"), Ok(vec![13]));
+        assert_eq!(manifest.my_values_with_prefix("r"), Ok(vec![11]));
+        // there are no departure fields at all
+        assert_eq!(part2(&manifest), Ok(1));
     }
 
-    // Maps key indexes to ticket value indexes -> this is where the result is stored.
-    let mut indexes = [None; FIELD_COUNT];
+    #[test]
+    fn unresolvable_fields() {
+        // without the last ticket, seat could go in column 1 or 2
+        let manifest = parse_input(EXAMPLE.trim_end_matches("\n5,14,9")).unwrap();
+        assert!(matches!(
+            manifest.field_columns(),
+            Err(AssignmentError::Ambiguous { .. })
+        ));
+        // no column has only seats between 18 and 19
+        let manifest = parse_input(&EXAMPLE.replace("seat: 0-13 or 16-19", "seat: 18-19")).unwrap();
+        assert!(matches!(
+            manifest.field_columns(),
+            Err(AssignmentError::NoAssignment { .. })
+        ));
+        let err = parse_input(&EXAMPLE.replace("seat:", "row:")).unwrap_err();
+        assert_eq!(err.line(), Some(3));
+    }
 
-    let tickets: Vec<_> = input
-        .other_tickets
-        .iter()
-        .filter(|t| input.is_valid(t))
-        .collect();
-    while !possible.is_empty() {
-        for i in 0..FIELD_COUNT {
-            if indexes[i] != None {
-                continue;
-            }
-            for ticket in tickets.iter() {
-                let field = &input.fields[i];
-                for j in 0..FIELD_COUNT {
-                    let value = ticket.values[j];
-                    if !field.matches_value(&value) {
-                        if let Some(entry) = possible.get_mut(&i) {
-                            entry.remove(&j);
-                        }
-                    }
-                }
-            }
-        }
+    #[test]
+    fn any_number_of_ranges() {
+        let field: Field = "wagon: 1-2 or 5-6 or 9-9".parse().unwrap();
+        assert!(field.matches_value(&9));
+        assert!(!field.matches_value(&7));
+        let field: Field = "train: 3-4".parse().unwrap();
+        assert_eq!(field.ranges.ranges(), &[3..=4]);
+        let field: Field = "cabin: 1-5 or 3-8".parse().unwrap();
+        assert_eq!(field.ranges.ranges(), &[1..=8]);
+    }
 
-        let confirmed_candidates = possible
-            .iter()
-            .filter(|(_, v)| v.len() == 1)
-            .flat_map(|(k, v)| Some((*k, *v.iter().next()?)))
-            .collect::<Vec<_>>();
-
-        for (key, value) in confirmed_candidates {
-            indexes[key] = Some(value);
-            possible.remove(&key);
-            // also remove from all other keys
-            for k in possible.clone().keys() {
-                possible.get_mut(&k).unwrap().remove(&value);
-            }
-        }
+    #[test]
+    fn rejects_reversed_ranges() {
+        assert!("wagon: 5-3".parse::<Field>().is_err());
+        assert!("wagon: 1-2 or 9-8".parse::<Field>().is_err());
+        let err = parse_input(&EXAMPLE.replace("row: 0-5", "row: 5-0")).unwrap_err();
+        assert_eq!(err.line(), Some(2));
     }
 
-    // the first 6 are the values that we want, get them out of our ticket
-    indexes[0..6]
-        .iter()
-        .map(|idx| idx.unwrap_or(1))
-        .map(|idx| input.my_ticket.values[idx] as u64)
-        .product()
+    #[test]
+    fn tickets_need_a_value_per_field() {
+        let err = parse_input(&EXAMPLE.replace("15,1,5", "15,1")).unwrap_err();
+        assert_eq!(err.line(), Some(10));
+    }
 }
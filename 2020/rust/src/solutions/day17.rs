@@ -1,6 +1,8 @@
-use std::convert::TryFrom;
+use crate::common::grid::Grid;
+use crate::common::parse_error::ParseError;
+use crate::common::vector_n::VectorN;
 use std::collections::HashSet;
-use std::convert::TryInto;
+use std::convert::TryFrom;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum State {
@@ -9,38 +11,31 @@ enum State {
 }
 
 impl TryFrom<char> for State {
-    type Error = &'static str;
+    type Error = ParseError;
     fn try_from(value: char) -> Result<Self, Self::Error> {
         match value {
             '#' => Ok(State::Active),
             '.' => Ok(State::Inactive),
-            _ => Err("Invalid character in input"),
+            _ => Err(ParseError::expected("'.' or '#'")),
         }
     }
 }
 
 #[derive(Debug, Clone)]
 struct Cube<const N: usize> {
-    active: HashSet<[i32; N]>,
+    active: HashSet<VectorN<i32, N>>,
 }
 
 impl<const N: usize> Cube<N> {
-    fn from_first_layer(layer: Vec<Vec<State>>) -> Self {
-        assert!(N >= 2, "Dimension must be at least 2");
-        let cells = layer.iter().enumerate().flat_map(|(y, row)| {
-            row.iter().enumerate().flat_map(move |(x, state)| {
-                let mut arr = [0; N];
-                arr[0] = x as i32;
-                arr[1] = y as i32;
-                match state {
-                    State::Active => Some(arr),
-                    State::Inactive => None,
-                }
+    fn from_first_layer(layer: &Grid<State>) -> Self {
+        let cells = layer
+            .enumerate()
+            .filter(|(_, state)| **state == State::Active)
+            .map(|(pos, _)| {
+                VectorN::from_vector2(pos.try_cast().expect("grid positions fit in an i32"))
             })
-        }).collect();
-        Cube {
-            active: cells,
-        }
+            .collect();
+        Cube { active: cells }
     }
 
     /// The total number of cells that are active
@@ -48,40 +43,8 @@ impl<const N: usize> Cube<N> {
         self.active.len()
     }
 
-    fn neighbors(&self, pos: &[i32; N], include_self: bool) -> Vec<[i32; N]> {
-
-        fn _neighbours(pos: &Vec<i32>, include_self: bool) -> Vec<Vec<i32>> {
-            let mut result = vec![];
-            if pos.len() == 1 {
-                let val = pos[0];
-                result.push(vec![val - 1]);
-                if include_self {
-                    result.push(vec![val]);
-                }
-                result.push(vec![val + 1]);
-            } else {
-                let subs = _neighbours(&pos[1..].to_vec(), true);
-                for x in pos[0] - 1..=pos[0] + 1 {
-                    for sub in &subs {
-                        let mut n = vec![];
-                        n.push(x);
-                        n.extend(sub.iter());
-                        if include_self || n != *pos {
-                            result.push(n);
-                        }
-                    }
-                }
-            }
-            result
-        }
-
-        _neighbours(&pos.to_vec(), include_self).into_iter().map(|n| {
-            n.try_into().unwrap()
-        }).collect()
-    }
-
     /// Get the state for this cell.
-    fn state_at(&self, pos: &[i32; N]) -> State {
+    fn state_at(&self, pos: &VectorN<i32, N>) -> State {
         if self.active.contains(pos) {
             State::Active
         } else {
@@ -90,10 +53,9 @@ impl<const N: usize> Cube<N> {
     }
 
     /// Compute the next state for this position after a game of life round.
-    fn next_state(&self, pos: &[i32; N]) -> State {
-        let neighbors = self.neighbors(&pos, false);
-        let active_neighbors = neighbors
-            .iter()
+    fn next_state(&self, pos: &VectorN<i32, N>) -> State {
+        let active_neighbors = pos
+            .neighbours(false)
             .filter(|pos| self.state_at(pos) == State::Active)
             .count();
         match self.state_at(pos) {
@@ -108,9 +70,7 @@ impl<const N: usize> Cube<N> {
         let mut new_active = HashSet::with_capacity(current_active);
         let mut processed = HashSet::with_capacity(current_active);
         for pos in self.active.iter() {
-            let ns = self.neighbors(pos, true)
-                .into_iter()
-                .filter(|nr| processed.insert(nr.clone()));
+            let ns = pos.neighbours(true).filter(|nr| processed.insert(*nr));
             for n in ns {
                 match self.next_state(&n) {
                     State::Active => new_active.insert(n),
@@ -122,18 +82,14 @@ impl<const N: usize> Cube<N> {
     }
 }
 
-
 #[aoc_generator(day17)]
-fn parse_input(input: &str) -> Vec<Vec<State>> {
-    input
-        .lines()
-        .map(|line| line.chars().map(|c| State::try_from(c).unwrap()).collect())
-        .collect()
+fn parse_input(input: &str) -> Result<Grid<State>, ParseError> {
+    input.parse()
 }
 
 #[aoc(day17, part1)]
-fn part1(input: &Vec<Vec<State>>) -> usize {
-    let mut cube: Cube<3> = Cube::from_first_layer(input.clone());
+fn part1(input: &Grid<State>) -> usize {
+    let mut cube: Cube<3> = Cube::from_first_layer(input);
     for _ in 0..6 {
         cube.game_of_life();
     }
@@ -141,8 +97,8 @@ fn part1(input: &Vec<Vec<State>>) -> usize {
 }
 
 #[aoc(day17, part2)]
-fn part2(input: &Vec<Vec<State>>) -> usize {
-    let mut cube: Cube<4> = Cube::from_first_layer(input.clone());
+fn part2(input: &Grid<State>) -> usize {
+    let mut cube: Cube<4> = Cube::from_first_layer(input);
     for _ in 0..6 {
         cube.game_of_life();
     }
@@ -1,131 +1,443 @@
-#[derive(Debug, Copy, Clone)]
-enum Operation {
+// --- Day 18: Operation Order ---
+// The homework is ordinary arithmetic with unusual operator precedence,
+// so both parts are one Pratt parser run with different precedence tables.
+
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operator {
     Add,
+    Sub,
     Mul,
+    Div,
 }
 
-#[derive(Debug, Clone)]
-enum Item {
-    Digit(u8),
-    Op(Operation),
-    Nested(Vec<Item>),
-}
-
-/// Parses a computation row into it's elements and the index to continue at, if applicable.
-fn sequence_parser(input: &str) -> (Vec<Item>, Option<usize>) {
-    use Item::*;
-    let mut result = vec![];
-    let mut i = 0;
-    let chars = input.chars().collect::<Vec<_>>();
-    while i < input.len() {
-        let c = chars[i];
-        match c {
-            '(' => {
-                let (nest, next) = sequence_parser(&input[i + 1..]);
-                result.push(Nested(nest));
-                if let Some(next) = next {
-                    i += next;
-                }
-            }
-            ')' => return (result, Some(i + 1)),
-            '+' => result.push(Op(Operation::Add)),
-            '*' => result.push(Op(Operation::Mul)),
-            '0'..='9' => result.push(Digit(c.to_digit(10).unwrap() as u8)),
-            _ => {}
+impl Operator {
+    fn apply(self, left: i64, right: i64) -> Result<i64, EvalError> {
+        match self {
+            Operator::Add => left.checked_add(right).ok_or(EvalError::Overflow),
+            Operator::Sub => left.checked_sub(right).ok_or(EvalError::Overflow),
+            Operator::Mul => left.checked_mul(right).ok_or(EvalError::Overflow),
+            Operator::Div if right == 0 => Err(EvalError::DivisionByZero),
+            Operator::Div => left.checked_div(right).ok_or(EvalError::Overflow),
         }
-        i += 1;
     }
-    (result, None)
 }
 
-#[aoc_generator(day18)]
-fn parse_input(input: &str) -> Vec<Vec<Item>> {
-    input
-        .lines()
-        .map(|line| {
-            let (result, _) = sequence_parser(line);
-            result
-        })
-        .collect()
-}
-
-fn compute_row(input: &Vec<Item>) -> u64 {
-    let mut total = 0;
-    let mut op = &Operation::Add;
-    for item in input {
-        use Item::*;
-        match item {
-            Op(next_op) => op = next_op,
-            Digit(value) => {
-                let val = *value as u64;
-                match op {
-                    Operation::Add => total += val,
-                    Operation::Mul => total *= val,
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Op(Operator),
+    Open,
+    Close,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Op(Operator::Add) => write!(f, "+"),
+            Token::Op(Operator::Sub) => write!(f, "-"),
+            Token::Op(Operator::Mul) => write!(f, "*"),
+            Token::Op(Operator::Div) => write!(f, "/"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+/// A tokenized line of homework, remembering where each token came from
+/// so that errors can point at it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row {
+    tokens: Vec<Token>,
+    columns: Vec<Range<usize>>,
+    /// The length of the line, where errors about a missing token point.
+    end: usize,
+}
+
+/// Splits a line into tokens, skipping whitespace.
+fn tokenize(line: &str) -> Result<Row, ParseError> {
+    let mut tokens = vec![];
+    let mut columns = vec![];
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            ' ' | '\t' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '+' => Token::Op(Operator::Add),
+            '-' => Token::Op(Operator::Sub),
+            '*' => Token::Op(Operator::Mul),
+            '/' => Token::Op(Operator::Div),
+            '0'..='9' => {
+                let mut end = start + 1;
+                // keep digits together, "12" is twelve
+                while let Some((i, _)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    end = i + 1;
                 }
+                let number = line[start..end].parse().map_err(|e| {
+                    ParseError::from(e)
+                        .with_text(&line[start..end])
+                        .at_columns(start..end)
+                })?;
+                Token::Number(number)
+            }
+            _ => {
+                return Err(ParseError::expected("numbers, + - * / or parentheses")
+                    .with_text(c.to_string())
+                    .at_columns(start..start + c.len_utf8()))
             }
-            Nested(items) => {
-                let nest_total = compute_row(items);
-                match op {
-                    Operation::Add => total += nest_total,
-                    Operation::Mul => total *= nest_total,
+        };
+        let end = chars.peek().map_or(line.len(), |(i, _)| *i);
+        tokens.push(token);
+        columns.push(start..line[..end].trim_end().len());
+    }
+    Ok(Row {
+        tokens,
+        columns,
+        end: line.len(),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a - b - c` is `a - (b - c)`
+    Right,
+}
+
+/// How tightly each binary operator binds, higher levels first.
+/// Operators that aren't in the table can't be used.
+#[derive(Debug, Clone, Default)]
+struct Precedence {
+    levels: Vec<(Operator, u8, Associativity)>,
+}
+
+#[allow(dead_code)]
+impl Precedence {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds (or replaces) the level of `op`.
+    fn with(mut self, op: Operator, level: u8, associativity: Associativity) -> Self {
+        self.levels.retain(|(o, _, _)| *o != op);
+        self.levels.push((op, level, associativity));
+        self
+    }
+
+    /// Every operator on the same level, worked out left to right.
+    fn left_to_right() -> Self {
+        use Operator::*;
+        [Add, Sub, Mul, Div]
+            .iter()
+            .fold(Self::new(), |p, op| p.with(*op, 1, Associativity::Left))
+    }
+
+    /// Addition and subtraction before multiplication and division.
+    fn addition_first() -> Self {
+        use Associativity::Left;
+        use Operator::*;
+        Self::new()
+            .with(Add, 2, Left)
+            .with(Sub, 2, Left)
+            .with(Mul, 1, Left)
+            .with(Div, 1, Left)
+    }
+
+    /// The usual rules, multiplication and division first.
+    fn standard() -> Self {
+        use Associativity::Left;
+        use Operator::*;
+        Self::new()
+            .with(Add, 1, Left)
+            .with(Sub, 1, Left)
+            .with(Mul, 2, Left)
+            .with(Div, 2, Left)
+    }
+
+    fn level(&self, op: Operator) -> Option<(u8, Associativity)> {
+        self.levels
+            .iter()
+            .find(|(o, _, _)| *o == op)
+            .map(|(_, level, associativity)| (*level, *associativity))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(i64),
+    Negate(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum EvalError {
+    Overflow,
+    DivisionByZero,
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EvalError::Overflow => write!(f, "the result doesn't fit in an i64"),
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+impl Expr {
+    fn evaluate(&self) -> Result<i64, EvalError> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Negate(inner) => inner.evaluate()?.checked_neg().ok_or(EvalError::Overflow),
+            Expr::Binary(op, left, right) => op.apply(left.evaluate()?, right.evaluate()?),
+        }
+    }
+}
+
+/// A Pratt parser, see `parse`.
+struct Parser<'a> {
+    row: &'a Row,
+    position: usize,
+    precedence: &'a Precedence,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.row.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    /// Where the token at `position` is, or the end of the line if it's past the last one.
+    fn columns(&self, position: usize) -> Range<usize> {
+        match self.row.columns.get(position) {
+            Some(columns) => columns.clone(),
+            None => self.row.end..self.row.end,
+        }
+    }
+
+    /// An error pointing at the token at `position`.
+    fn unexpected(&self, position: usize, expected: &str) -> ParseError {
+        let error = ParseError::expected(expected).at_columns(self.columns(position));
+        match self.row.tokens.get(position) {
+            Some(token) => error.with_text(token.to_string()),
+            None => error.with_text("end of line"),
+        }
+    }
+
+    /// Parses an operand, a number, negation or something in parentheses.
+    /// `after` is the position of the operator it belongs to, if any.
+    fn operand(&mut self, after: Option<usize>) -> Result<Expr, ParseError> {
+        let position = self.position;
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Open) => {
+                let inner = self.expression(0)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    // point at the bracket that wasn't closed
+                    None => Err(self.unexpected(position, "a matching ')'")),
+                    Some(_) => Err(self.unexpected(self.position - 1, "an operator or ')'")),
                 }
             }
+            // negation binds tighter than every binary operator
+            Some(Token::Op(Operator::Sub)) => Ok(Expr::Negate(Box::new(
+                self.expression_after(u16::MAX, position)?,
+            ))),
+            _ => match after {
+                Some(op) => Err(self.unexpected(op, "a number or '(' after the operator")),
+                None => Err(self.unexpected(position, "a number or '('")),
+            },
         }
     }
-    total
-}
-
-fn force_addition_precedence(input: Vec<Item>) -> Vec<Item> {
-    use Item::*;
-    let mut result = vec![];
-    let mut temp = vec![];
-    for item in input {
-        match item {
-            // chunk results prior to a multiplication,
-            // which will raise the precendence of the addition
-            Op(Operation::Mul) => {
-                result.push(Nested(temp.clone()));
-                result.push(item);
-                temp.clear();
+
+    fn expression(&mut self, min_level: u16) -> Result<Expr, ParseError> {
+        let left = self.operand(None)?;
+        self.operators(left, min_level)
+    }
+
+    /// `expression`, for the right hand side of the operator at `op_position`.
+    fn expression_after(&mut self, min_level: u16, op_position: usize) -> Result<Expr, ParseError> {
+        let left = self.operand(Some(op_position))?;
+        self.operators(left, min_level)
+    }
+
+    /// Folds operators into `left` up to the first that binds less tightly than `min_level`.
+    fn operators(&mut self, mut left: Expr, min_level: u16) -> Result<Expr, ParseError> {
+        while let Some(Token::Op(op)) = self.peek() {
+            let position = self.position;
+            let (level, associativity) = self.precedence.level(op).ok_or_else(|| {
+                self.unexpected(position, "an operator from the precedence table")
+            })?;
+            let level = u16::from(level);
+            if level < min_level {
+                break;
             }
-            Nested(items) => temp.push(Nested(force_addition_precedence(items))),
-            _ => temp.push(item),
+            self.next();
+            let right = self.expression_after(
+                match associativity {
+                    Associativity::Left => level + 1,
+                    Associativity::Right => level,
+                },
+                position,
+            )?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
         }
+        Ok(left)
+    }
+}
+
+/// Builds the expression tree for a line of tokens.
+fn parse(row: &Row, precedence: &Precedence) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        row,
+        position: 0,
+        precedence,
+    };
+    let expr = parser.expression(0)?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(Token::Close) => Err(parser.unexpected(parser.position, "a '(' before this ')'")),
+        Some(_) => Err(parser.unexpected(parser.position, "an operator or the end of the line")),
     }
-    // get last remaining temp items
-    result.push(Nested(temp));
-    result
+}
+
+/// Tokenizes a line, and makes sure it's a well formed expression.
+fn row_parser(line: &str) -> Result<Row, ParseError> {
+    let row = tokenize(line)?;
+    // every operator has a level here, and the levels don't change whether it parses
+    parse(&row, &Precedence::left_to_right())?;
+    Ok(row)
+}
+
+#[aoc_generator(day18)]
+fn parse_input(input: &str) -> Result<Vec<Row>, ParseError> {
+    ParseMode::current().parse_lines_with(input, row_parser)
+}
+
+/// The sum of every line of the homework.
+fn homework(rows: &[Row], precedence: &Precedence) -> Result<i64, Box<dyn Error>> {
+    let mut total: i64 = 0;
+    for (line, row) in rows.iter().enumerate() {
+        let value = parse(row, precedence)
+            .map_err(|e| e.at_line(line + 1))?
+            .evaluate()?;
+        total = total.checked_add(value).ok_or(EvalError::Overflow)?;
+    }
+    Ok(total)
 }
 
 #[aoc(day18, part1)]
-fn part1(input: &Vec<Vec<Item>>) -> u64 {
-    input.iter().map(|row| compute_row(row)).sum()
+fn part1(input: &[Row]) -> Result<i64, Box<dyn Error>> {
+    homework(input, &Precedence::left_to_right())
 }
 
 #[aoc(day18, part2)]
-fn part2(input: &Vec<Vec<Item>>) -> u64 {
-    input
-        .clone()
-        .into_iter()
-        .map(|row| force_addition_precedence(row))
-        .map(|row| compute_row(&row))
-        .sum()
+fn part2(input: &[Row]) -> Result<i64, Box<dyn Error>> {
+    homework(input, &Precedence::addition_first())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn evaluate(line: &str, precedence: &Precedence) -> Result<i64, EvalError> {
+        parse(&row_parser(line).unwrap(), precedence)
+            .unwrap()
+            .evaluate()
+    }
+
     #[test]
-    fn test_parse() {
-        let str = "1 + (2 * (3 * 3))";
-        println!("{:?}", sequence_parser(str));
+    fn test_tokenize() {
+        use Token::*;
+        let row = tokenize("12 +(3)").unwrap();
+        assert_eq!(
+            row.tokens,
+            vec![Number(12), Op(Operator::Add), Open, Number(3), Close]
+        );
+        assert_eq!(row.columns, vec![0..2, 3..4, 4..5, 5..6, 6..7]);
+        assert_eq!(tokenize("1 % 2").unwrap_err().columns(), Some(2..3));
+        // too big for an i64
+        let err = tokenize("1 + 99999999999999999999 * 2").unwrap_err();
+        assert_eq!(err.columns(), Some(4..24));
+        assert_eq!(err.text(), Some("99999999999999999999"));
     }
 
     #[test]
     fn test_compute() {
-        let str = "1 + (2 * (3 * 3))";
-        let (parsed, _) = sequence_parser(str);
-        let total = compute_row(&parsed);
-        assert_eq!(total, 19);
+        let line = "1 + 2 * 3 + 4 * 5 + 6";
+        assert_eq!(evaluate(line, &Precedence::left_to_right()), Ok(71));
+        assert_eq!(evaluate(line, &Precedence::addition_first()), Ok(231));
+        assert_eq!(evaluate(line, &Precedence::standard()), Ok(33));
+        let line = "1 + (2 * (3 * 3))";
+        assert_eq!(evaluate(line, &Precedence::left_to_right()), Ok(19));
+    }
+
+    #[test]
+    fn subtraction_and_division() {
+        let standard = Precedence::standard();
+        assert_eq!(evaluate("100 - 20 / 4 - 3", &standard), Ok(92));
+        assert_eq!(evaluate("-2 * -(3 - 10)", &standard), Ok(-14));
+        let right = standard.with(Operator::Sub, 1, Associativity::Right);
+        assert_eq!(evaluate("10 - 4 - 3", &right), Ok(9));
+    }
+
+    #[test]
+    fn checked_evaluation() {
+        let standard = Precedence::standard();
+        assert_eq!(
+            evaluate("1 / (2 - 2)", &standard),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            evaluate("9223372036854775807 + 1", &standard),
+            Err(EvalError::Overflow)
+        );
+    }
+
+    #[test]
+    fn operators_missing_from_the_table() {
+        let row = tokenize("1 / 2").unwrap();
+        let precedence = Precedence::new().with(Operator::Add, 1, Associativity::Left);
+        assert_eq!(parse(&row, &precedence).unwrap_err().columns(), Some(2..3));
+    }
+
+    fn error_at(line: &str) -> (Option<Range<usize>>, String) {
+        let err = row_parser(line).unwrap_err();
+        (err.columns(), err.text().unwrap().to_string())
+    }
+
+    #[test]
+    fn malformed_expressions() {
+        assert_eq!(error_at("1 + (2 * 3"), (Some(4..5), "(".to_string()));
+        assert_eq!(error_at("1 + 2)"), (Some(5..6), ")".to_string()));
+        assert_eq!(error_at("1 + * 3"), (Some(2..3), "+".to_string()));
+        assert_eq!(error_at("2 *"), (Some(2..3), "*".to_string()));
+        assert_eq!(error_at("(1 2)"), (Some(3..4), "2".to_string()));
+        assert_eq!(error_at("3 4"), (Some(2..3), "4".to_string()));
+        assert_eq!(error_at(""), (Some(0..0), "end of line".to_string()));
+        assert_eq!(error_at("()"), (Some(1..2), ")".to_string()));
+    }
+
+    #[test]
+    fn rejects_malformed_homework() {
+        let err = parse_input("1 + 2\n(3 * 4\n5").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+        assert_eq!(err.columns(), Some(0..1));
     }
 }
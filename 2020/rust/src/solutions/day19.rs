@@ -1,6 +1,16 @@
+// --- Day 19: Monster Messages ---
+// The rules are a grammar. Without recursion they describe a regular language,
+// so they're compiled to a regex. The recursive rules in part 2 either repeat
+// (`8: 42 | 42 8` is `42+`) or balance (`11: 42 31 | 42 11 31` is 42^n 31^n),
+// and the balanced ones are unrolled just deep enough for the longest message.
+// Anything else falls back to CYK.
+
+use crate::common::grammar::{Grammar, Symbol};
+use crate::common::parse_error::{parse_match, ParseError};
+use crate::common::parse_mode::ParseMode;
 use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashMap;
+use regex::{Regex, RegexBuilder};
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 
 type RuleID = u16;
@@ -14,25 +24,36 @@ enum Resolver {
 }
 
 impl TryFrom<&str> for Resolver {
-    type Error = &'static str;
+    type Error = ParseError;
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         lazy_static! {
-            static ref CHARACTER: Regex = Regex::new("\"([a-z])\"").unwrap();
+            static ref CHARACTER: Regex = Regex::new("^\"([a-z])\"$").unwrap();
         }
         if let Some(cap) = CHARACTER.captures(s) {
-            let c = cap
-                .get(1)
-                .ok_or("No Character matches")?
-                .as_str()
-                .parse()
-                .unwrap();
+            let c = parse_match(cap.get(1).unwrap())?;
             Ok(Resolver::Literal(c))
         } else {
             // dependency
             let dep = s
-                .split("|")
-                .map(|group| group.split(" ").flat_map(|num| num.parse().ok()).collect())
-                .collect();
+                .split('|')
+                .map(|group| {
+                    // every rule has to match something, or the matchers would loop
+                    if group.trim().is_empty() {
+                        return Err(ParseError::expected("a rule number or a quoted character")
+                            .with_text(s));
+                    }
+                    group
+                        .split_whitespace()
+                        .map(|num| {
+                            num.parse().map_err(|e| {
+                                ParseError::from(e)
+                                    .with_expected("a rule number or a quoted character")
+                                    .with_text(num)
+                            })
+                        })
+                        .collect()
+                })
+                .collect::<Result<_, _>>()?;
             Ok(Resolver::Dep(dep))
         }
     }
@@ -45,15 +66,18 @@ struct Rule {
 }
 
 impl TryFrom<&str> for Rule {
-    type Error = &'static str;
+    type Error = ParseError;
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         lazy_static! {
-            static ref RULE: Regex = Regex::new(r"(\d+): (.*)").unwrap();
+            static ref RULE: Regex = Regex::new(r"^(\d+): (.*)$").unwrap();
         }
-        let caps = RULE.captures(s).unwrap();
-        let rule_num = caps.get(1).unwrap().as_str().parse().unwrap();
-        let resolver = caps.get(2).unwrap().as_str();
-        let resolved = Resolver::try_from(resolver).unwrap();
+        let caps = RULE
+            .captures(s)
+            .ok_or_else(|| ParseError::expected("\"<id>: <rule>\"").with_text(s))?;
+        let rule_num = parse_match(caps.get(1).unwrap())?;
+        let resolver = caps.get(2).unwrap();
+        let resolved = Resolver::try_from(resolver.as_str())
+            .map_err(|e| e.at_columns(resolver.start()..resolver.end()))?;
         Ok(Rule::new(rule_num, resolved))
     }
 }
@@ -64,6 +88,60 @@ impl Rule {
     }
 }
 
+/// How a rule refers back to itself.
+#[derive(Debug, Clone, PartialEq)]
+enum Recursion {
+    None,
+    /// `r: a | r a`, one or more `a`
+    Left(Vec<RuleID>),
+    /// `r: a | a r`, one or more `a`
+    Right(Vec<RuleID>),
+    /// `r: a b | a r b`, some number of `a` followed by as many `b`
+    Balanced(Vec<RuleID>, Vec<RuleID>),
+    /// Anything else, including rules that reach themselves through others.
+    Other,
+}
+
+/// How a `Matcher` decides if a message matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strategy {
+    /// There's no recursion, so the rules are a single regex.
+    Regex,
+    /// Recursive rules only repeat, which a regex can do with `+`.
+    Repetition,
+    /// Balanced rules were unrolled this many times, enough for the longest message.
+    BoundedExpansion { depth: usize },
+    /// Recursion with no regex equivalent, matched with the CYK algorithm.
+    Cyk,
+}
+
+/// Rules compiled for matching whole messages against a start rule.
+#[derive(Debug)]
+struct Matcher<'a> {
+    manifest: &'a Manifest,
+    start: RuleID,
+    strategy: Strategy,
+    /// Only `None` for `Strategy::Cyk`
+    regex: Option<Regex>,
+}
+
+impl<'a> Matcher<'a> {
+    #[allow(dead_code)]
+    fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(text),
+            None => self.manifest.cyk(text, self.start),
+        }
+    }
+}
+
+/// Raised while building a regex for rules that don't have one.
+struct Irregular;
+
 #[derive(Debug, Clone)]
 struct Manifest {
     rules: HashMap<RuleID, Rule>,
@@ -80,105 +158,304 @@ impl Manifest {
         self.rules.insert(id, new_rule);
     }
 
-    fn does_match_rules(&self, text: &str) -> bool {
-        type Key<'x> = (&'x [char], RuleID);
+    fn dependencies(&self, id: RuleID) -> impl Iterator<Item = RuleID> + '_ {
+        let dep = match &self.rules[&id].resolver {
+            Resolver::Literal(_) => None,
+            Resolver::Dep(dep) => Some(dep),
+        };
+        dep.into_iter().flatten().flatten().copied()
+    }
 
-        fn does_match_multiple<'a>(
-            memo: &mut HashMap<Key<'a>, bool>,
-            man: &Manifest,
-            chars: &'a [char],
-            rules: &[RuleID],
-        ) -> bool {
-            let no_chars = chars.is_empty();
-            let no_rules = rules.is_empty();
-            if no_chars && no_rules {
-                return true;
-            }
-            if no_chars {
-                return false;
-            }
-            if no_rules {
-                return false;
+    /// Every rule that `id` depends on, directly or not, including itself.
+    fn reachable(&self, id: RuleID) -> HashSet<RuleID> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if seen.insert(id) {
+                stack.extend(self.dependencies(id));
             }
+        }
+        seen
+    }
 
-            let other_rules = &rules[1..];
-            for idx in 0..chars.len() {
-                let idx = idx + 1;
-                if does_match_single(memo, man, &chars[..idx], rules[0])
-                    && does_match_multiple(memo, man, &chars[idx..], &other_rules)
+    /// Does `id` depend on itself through some other rule?
+    fn is_mutually_recursive(&self, id: RuleID) -> bool {
+        self.dependencies(id)
+            .filter(|dep| *dep != id)
+            .any(|dep| self.reachable(dep).contains(&id))
+    }
+
+    fn recursion(&self, id: RuleID) -> Recursion {
+        let alternatives = match &self.rules[&id].resolver {
+            Resolver::Literal(_) => return Recursion::None,
+            Resolver::Dep(dep) => dep,
+        };
+        if self.is_mutually_recursive(id) {
+            return Recursion::Other;
+        }
+        let (recursive, base): (Vec<_>, Vec<_>) = alternatives
+            .iter()
+            .partition(|alternative| alternative.contains(&id));
+        match (&recursive[..], &base[..]) {
+            ([], _) => Recursion::None,
+            ([recursive], [base]) if recursive.iter().filter(|r| **r == id).count() == 1 => {
+                let at = recursive.iter().position(|r| *r == id).unwrap();
+                let (prefix, suffix) = (&recursive[..at], &recursive[at + 1..]);
+                if suffix.is_empty() && prefix == &base[..] {
+                    Recursion::Right(base.to_vec())
+                } else if prefix.is_empty() && suffix == &base[..] {
+                    Recursion::Left(base.to_vec())
+                } else if !prefix.is_empty()
+                    && !suffix.is_empty()
+                    && [prefix, suffix].concat() == **base
                 {
-                    return true;
+                    Recursion::Balanced(prefix.to_vec(), suffix.to_vec())
+                } else {
+                    Recursion::Other
                 }
             }
+            _ => Recursion::Other,
+        }
+    }
 
-            false
+    /// The fewest characters each rule can match, `usize::MAX` for rules
+    /// that can't match anything (or only something absurdly long).
+    fn min_lengths(&self) -> HashMap<RuleID, usize> {
+        let mut lengths: HashMap<RuleID, usize> =
+            self.rules.keys().map(|id| (*id, usize::MAX)).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, rule) in self.rules.iter() {
+                let length = match &rule.resolver {
+                    Resolver::Literal(_) => 1,
+                    Resolver::Dep(dep) => dep
+                        .iter()
+                        .map(|seq| {
+                            seq.iter()
+                                .map(|r| lengths[r])
+                                .fold(0usize, usize::saturating_add)
+                        })
+                        .min()
+                        .unwrap_or(usize::MAX),
+                };
+                if length < lengths[id] {
+                    lengths.insert(*id, length);
+                    changed = true;
+                }
+            }
         }
+        lengths
+    }
 
-        fn does_match_single<'a>(
-            memo: &mut HashMap<Key<'a>, bool>,
-            man: &Manifest,
-            chars: &'a [char],
-            rule: RuleID,
-        ) -> bool {
-            let key = (chars, rule);
-            if let Some(result) = memo.get(&key) {
-                return *result;
+    /// The regex for `id`, with balanced rules unrolled `depth` times.
+    fn pattern(
+        &self,
+        id: RuleID,
+        depth: usize,
+        cache: &mut HashMap<RuleID, String>,
+    ) -> Result<String, Irregular> {
+        if let Some(pattern) = cache.get(&id) {
+            return Ok(pattern.clone());
+        }
+        let mut sequence = |seq: &[RuleID]| -> Result<String, Irregular> {
+            seq.iter().map(|r| self.pattern(*r, depth, cache)).collect()
+        };
+        let pattern = match self.recursion(id) {
+            Recursion::None => match &self.rules[&id].resolver {
+                Resolver::Literal(c) => regex::escape(&c.to_string()),
+                Resolver::Dep(dep) => {
+                    let alternatives: Vec<String> = dep
+                        .iter()
+                        .map(|seq| sequence(seq))
+                        .collect::<Result<_, _>>()?;
+                    format!("(?:{})", alternatives.join("|"))
+                }
+            },
+            Recursion::Left(seq) | Recursion::Right(seq) => format!("(?:{})+", sequence(&seq)?),
+            Recursion::Balanced(prefix, suffix) => {
+                let (prefix, suffix) = (sequence(&prefix)?, sequence(&suffix)?);
+                // a(?:a(?:ab)?b)?b matches ab, aabb and aaabbb
+                (1..depth).fold(format!("{}{}", prefix, suffix), |inner, _| {
+                    format!("{}(?:{})?{}", prefix, inner, suffix)
+                })
             }
-            let rule = man.rules.get(&rule).unwrap();
+            Recursion::Other => return Err(Irregular),
+        };
+        cache.insert(id, pattern.clone());
+        Ok(pattern)
+    }
 
-            match &rule.resolver {
-                Resolver::Literal(c) => {
-                    let is_match = [*c] == chars;
-                    memo.insert(key, is_match);
-                    is_match
+    /// Prepares to match messages against rule `start`, picking the fastest
+    /// strategy that works for these rules and messages.
+    fn compile(&self, start: RuleID) -> Matcher<'_> {
+        let reachable = self.reachable(start);
+        let recursion: Vec<_> = reachable.iter().map(|id| self.recursion(*id)).collect();
+        let lengths = self.min_lengths();
+        let longest = self.texts.iter().map(|t| t.chars().count()).max();
+        // how many times each balanced rule could repeat in the longest message
+        let depth = recursion
+            .iter()
+            .filter_map(|r| match r {
+                Recursion::Balanced(prefix, suffix) => {
+                    let min = prefix
+                        .iter()
+                        .chain(suffix)
+                        .map(|id| lengths[id])
+                        .fold(0usize, usize::saturating_add);
+                    // a usize::MAX minimum never fits in a message, so never repeats
+                    Some(longest.unwrap_or(0) / min.max(1))
                 }
-                Resolver::Dep(dep) => {
-                    for conjunction in dep {
-                        if does_match_multiple(memo, man, chars, conjunction) {
-                            memo.insert(key, true);
-                            return true;
+                _ => None,
+            })
+            .max();
+        let strategy = if recursion.contains(&Recursion::Other) {
+            Strategy::Cyk
+        } else if let Some(depth) = depth {
+            Strategy::BoundedExpansion {
+                depth: depth.max(1),
+            }
+        } else if recursion.iter().any(|r| *r != Recursion::None) {
+            Strategy::Repetition
+        } else {
+            Strategy::Regex
+        };
+        let regex = match strategy {
+            Strategy::Cyk => None,
+            Strategy::BoundedExpansion { depth } => self.build_regex(start, depth),
+            _ => self.build_regex(start, 1),
+        };
+        Matcher {
+            manifest: self,
+            start,
+            strategy: if regex.is_some() {
+                strategy
+            } else {
+                Strategy::Cyk
+            },
+            regex,
+        }
+    }
+
+    /// `None` if the rules are irregular, or too big for the regex crate.
+    fn build_regex(&self, start: RuleID, depth: usize) -> Option<Regex> {
+        let pattern = self.pattern(start, depth, &mut HashMap::new()).ok()?;
+        RegexBuilder::new(&format!("^{}$", pattern))
+            .size_limit(1 << 26)
+            .build()
+            .ok()
+    }
+
+    /// Does the whole of `text` match rule `start`?
+    ///
+    /// Works for any rules, by finding every rule that matches each span of the text,
+    /// shortest spans first.
+    fn cyk(&self, text: &str, start: RuleID) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let n = chars.len();
+        if n == 0 {
+            return false;
+        }
+        // spans[i][len] is the rules that match the `len` characters from `i`
+        let mut spans: Vec<Vec<HashSet<RuleID>>> = vec![vec![HashSet::new(); n + 1]; n];
+        for len in 1..=n {
+            for i in 0..=n - len {
+                // rules like `r: s` match the same span as `s`, so repeat until settled
+                let mut changed = true;
+                while changed {
+                    changed = false;
+                    for (id, rule) in self.rules.iter() {
+                        if spans[i][len].contains(id) {
+                            continue;
+                        }
+                        let matched = match &rule.resolver {
+                            Resolver::Literal(c) => len == 1 && chars[i] == *c,
+                            Resolver::Dep(dep) => dep
+                                .iter()
+                                .any(|seq| Self::sequence_matches(&spans, seq, i, i + len)),
+                        };
+                        if matched {
+                            spans[i][len].insert(*id);
+                            changed = true;
                         }
                     }
-                    memo.insert(key, false);
-                    false
                 }
             }
         }
+        spans[0][n].contains(&start)
+    }
 
-        let chars = text.chars().into_iter().collect::<Vec<_>>();
-        let chars = chars.as_slice();
-        // memoization for the dynamic programming algorithm
-        let mut dp = HashMap::<Key, bool>::new();
-        does_match_single(&mut dp, self, &chars, 0)
+    /// Can `seq` be split over `from..to`, using the spans found so far?
+    fn sequence_matches(
+        spans: &[Vec<HashSet<RuleID>>],
+        seq: &[RuleID],
+        from: usize,
+        to: usize,
+    ) -> bool {
+        match seq {
+            [] => from == to,
+            [last] => from < to && spans[from][to - from].contains(last),
+            [first, rest @ ..] => (from + 1..to).any(|end| {
+                spans[from][end - from].contains(first)
+                    && Self::sequence_matches(spans, rest, end, to)
+            }),
+        }
+    }
+
+    /// The rules as a general grammar, for finding out why a message matches or not.
+    #[allow(dead_code)]
+    fn grammar(&self) -> Grammar<RuleID> {
+        self.rules
+            .values()
+            .fold(Grammar::new(), |grammar, rule| match &rule.resolver {
+                Resolver::Literal(c) => {
+                    grammar.with_rule(rule.id, vec![vec![Symbol::Terminal(c.to_string())]])
+                }
+                Resolver::Dep(dep) => grammar.with_rule(
+                    rule.id,
+                    dep.iter()
+                        .map(|seq| seq.iter().map(|id| Symbol::Nonterminal(*id)).collect())
+                        .collect(),
+                ),
+            })
     }
 
     fn match_count(&self) -> usize {
-        self.texts
-            .iter()
-            .filter(|t| self.does_match_rules(t))
-            .count()
+        let matcher = self.compile(0);
+        self.texts.iter().filter(|t| matcher.is_match(t)).count()
     }
 }
 
 impl TryFrom<&str> for Manifest {
-    type Error = &'static str;
+    type Error = ParseError;
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        let mut parts = s.split("\n\n").into_iter();
-        let rules = parts.next().unwrap();
-        let rules = rules
-            .lines()
-            .flat_map(|line| Rule::try_from(line).ok())
+        let mut parts = s.splitn(2, "\n\n");
+        let rules = parts.next().unwrap_or_default();
+        let rules: HashMap<_, _> = ParseMode::current()
+            .parse_lines_with(rules, |line| Rule::try_from(line))?
+            .into_iter()
             .map(|rule| (rule.id, rule))
             .collect();
-        let texts = parts.next().unwrap();
+        // every rule that is depended on must exist, otherwise matching can't complete
+        for rule in rules.values() {
+            if let Resolver::Dep(dep) = &rule.resolver {
+                if let Some(missing) = dep.iter().flatten().find(|id| !rules.contains_key(id)) {
+                    return Err(ParseError::expected("every referenced rule to be defined")
+                        .with_text(format!("rule {} depends on rule {}", rule.id, missing)));
+                }
+            }
+        }
+        let texts = parts
+            .next()
+            .ok_or_else(|| ParseError::expected("a blank line between the rules and messages"))?;
         let texts = texts.lines().map(|line| line.to_string()).collect();
         Ok(Manifest::new(rules, texts))
     }
 }
 
 #[aoc_generator(day19)]
-fn parse_input(input: &str) -> Manifest {
-    Manifest::try_from(input).unwrap()
+fn parse_input(input: &str) -> Result<Manifest, ParseError> {
+    Manifest::try_from(input)
 }
 
 #[aoc(day19, part1)]
@@ -197,17 +474,76 @@ fn part2(input: &Manifest) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn manifest(rules: &str, texts: &str) -> Manifest {
+        Manifest::try_from(format!("{}\n\n{}", rules, texts).as_str()).unwrap()
+    }
+
     #[test]
     fn example() {
-        let p = "0: 1 | 1 0
-1: \"a\"
-
-a
-aaa
-aaaaaab
-b";
-        let man = Manifest::try_from(p).unwrap();
-        println!("{:?}", man);
+        let man = manifest("0: 1 | 1 0\n1: \"a\"", "a\naaa\naaaaaab\nb");
+        let matcher = man.compile(0);
+        assert_eq!(matcher.strategy(), Strategy::Repetition);
         assert_eq!(man.match_count(), 2);
     }
+
+    #[test]
+    fn detects_recursion() {
+        let man = manifest(
+            "0: 1 2\n1: 3 | 1 3\n2: 3 4 | 3 2 4\n3: \"a\"\n4: \"b\"\n5: 6\n6: 3 | 5 4",
+            "",
+        );
+        assert_eq!(man.recursion(0), Recursion::None);
+        assert_eq!(man.recursion(1), Recursion::Left(vec![3]));
+        assert_eq!(man.recursion(2), Recursion::Balanced(vec![3], vec![4]));
+        assert_eq!(man.recursion(5), Recursion::Other);
+    }
+
+    #[test]
+    fn strategies_agree() {
+        // 0 is a+ a^n b^n, or with 6 also `a b*`, which isn't handled by a regex
+        let rules = "0: 1 2\n1: 3 | 3 1\n2: 3 4 | 3 2 4\n3: \"a\"\n4: \"b\"\n\
+                     5: 6\n6: 3 | 5 4\n7: 8\n8: 3 4";
+        let texts = [
+            "aab", "aaabb", "aabb", "ab", "aaaaabbb", "abab", "a", "abbb",
+        ];
+        let man = manifest(rules, &texts.join("\n"));
+        let matcher = man.compile(0);
+        assert_eq!(matcher.strategy(), Strategy::BoundedExpansion { depth: 4 });
+        assert_eq!(man.compile(5).strategy(), Strategy::Cyk);
+        assert_eq!(man.compile(7).strategy(), Strategy::Regex);
+        for text in texts.iter() {
+            assert_eq!(matcher.is_match(text), man.cyk(text, 0), "{}", text);
+        }
+        let matched: Vec<_> = texts.iter().filter(|t| matcher.is_match(t)).collect();
+        assert_eq!(matched, vec![&"aab", &"aaabb", &"aaaaabbb"]);
+        let matched: Vec<_> = texts.iter().filter(|t| man.cyk(t, 5)).collect();
+        assert_eq!(matched, vec![&"ab", &"a", &"abbb"]);
+    }
+
+    #[test]
+    fn unproductive_rules() {
+        // 3 never finishes, so 1 and 0 can't match anything either
+        let man = manifest("0: 1 0 2 | 1 2\n1: 3\n2: \"b\"\n3: 3 2", "bb\nbbb");
+        assert_eq!(man.min_lengths()[&1], usize::MAX);
+        let matcher = man.compile(0);
+        assert_eq!(matcher.strategy(), Strategy::Cyk);
+        assert_eq!(man.match_count(), 0);
+    }
+
+    #[test]
+    fn as_a_grammar() {
+        let man = manifest("0: 1 2\n1: 3 | 3 1\n2: 3 4 | 3 2 4\n3: \"a\"\n4: \"b\"", "");
+        let grammar = man.grammar();
+        assert!(grammar.recognise(0, "aaabb"));
+        assert!(!grammar.recognise(0, "aabb"));
+        assert!(grammar.parse(0, "aaab").is_some());
+        let short: Vec<_> = grammar.enumerate(0, 4).into_iter().collect();
+        assert_eq!(short, vec!["aaab", "aab"]);
+    }
+
+    #[test]
+    fn rejects_empty_alternatives() {
+        assert!(Manifest::try_from("0: 1 |\n1: \"a\"\n\na").is_err());
+    }
 }
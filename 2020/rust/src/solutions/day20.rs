@@ -0,0 +1,386 @@
+// --- Day 20: Jurassic Jigsaw ---
+// Every edge is unique apart from the one tile it joins to, so corners are
+// the tiles with 2 unmatched edges, and the rest of the image can be filled in
+// greedily one row at a time from a corner.
+
+use crate::common::direction::Direction;
+use crate::common::grid::Grid;
+use crate::common::parse_error::{parse_match, ParseError};
+use crate::common::transform::Transform2;
+use crate::common::vector2::Vector2;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+const SEA_MONSTER: [&str; 3] = [
+    "                  # ",
+    "#    ##    ##    ###",
+    " #  #  #  #  #  #   ",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pixel {
+    Filled,
+    Empty,
+}
+
+impl TryFrom<char> for Pixel {
+    type Error = ParseError;
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '#' => Ok(Pixel::Filled),
+            '.' => Ok(Pixel::Empty),
+            _ => Err(ParseError::expected("'.' or '#'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tile {
+    id: u64,
+    image: Grid<Pixel>,
+}
+
+impl Tile {
+    /// The pixels along one side as bits, read left to right or top to bottom.
+    fn edge(&self, side: Direction) -> u32 {
+        let image = &self.image;
+        let pixels: Box<dyn Iterator<Item = &Pixel>> = match side {
+            Direction::Up => Box::new(image.row(0).iter()),
+            Direction::Down => Box::new(image.row(image.height() - 1).iter()),
+            Direction::Left => Box::new(image.column(0)),
+            Direction::Right => Box::new(image.column(image.width() - 1)),
+        };
+        pixels.fold(0, |acc, p| (acc << 1) | (*p == Pixel::Filled) as u32)
+    }
+
+    /// The edge in a form that's the same whichever way round it's read,
+    /// so edges can be matched up before the tiles are oriented.
+    fn canonical_edge(&self, side: Direction) -> u32 {
+        let edge = self.edge(side);
+        let len = match side {
+            Direction::Up | Direction::Down => self.image.width(),
+            Direction::Left | Direction::Right => self.image.height(),
+        };
+        let reversed = edge.reverse_bits() >> (32 - len);
+        edge.min(reversed)
+    }
+
+    fn transformed(&self, transform: Transform2) -> Self {
+        Tile {
+            id: self.id,
+            image: self.image.transformed(transform),
+        }
+    }
+
+    /// The tile in each of its 8 orientations.
+    fn orientations(&self) -> impl Iterator<Item = Tile> + '_ {
+        Transform2::ALL.iter().map(move |t| self.transformed(*t))
+    }
+}
+
+/// Parses one block of the input, starting at `first_line`.
+fn parse_tile(block: &str, first_line: usize) -> Result<Tile, ParseError> {
+    lazy_static! {
+        static ref HEADER: Regex = Regex::new(r"^Tile (\d+):$").unwrap();
+    }
+    let (header, image) = block.split_at(block.find('\n').unwrap_or(block.len()));
+    let id = match HEADER.captures(header) {
+        Some(cap) => parse_match(cap.get(1).unwrap()),
+        None => Err(ParseError::expected("\"Tile <id>:\"").with_text(header)),
+    }
+    .map_err(|e| e.at_line(first_line))?;
+    let image: Grid<Pixel> = image
+        .trim_start_matches('\n')
+        .parse()
+        .map_err(|e: ParseError| {
+            let line = e.line().unwrap_or(0);
+            e.at_line(first_line + line)
+        })?;
+    // edges are stored as the bits of a u32
+    if image.width() != image.height() || !(2..=32).contains(&image.width()) {
+        return Err(ParseError::expected("a square tile, 2 to 32 pixels wide").at_line(first_line));
+    }
+    Ok(Tile { id, image })
+}
+
+#[aoc_generator(day20)]
+fn parse_input(input: &str) -> Result<Vec<Tile>, ParseError> {
+    let mut tiles = vec![];
+    let mut line = 1;
+    for block in input.split("\n\n") {
+        tiles.push(parse_tile(block, line)?);
+        line += block.lines().count() + 1;
+    }
+    Ok(tiles)
+}
+
+/// How many tiles each canonical edge appears on.
+fn edge_counts(tiles: &[Tile]) -> HashMap<u32, usize> {
+    let mut counts = HashMap::new();
+    for tile in tiles {
+        for side in Direction::ALL.iter() {
+            *counts.entry(tile.canonical_edge(*side)).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Does this side of the tile sit on the outside of the picture?
+fn is_outer_edge(counts: &HashMap<u32, usize>, tile: &Tile, side: Direction) -> bool {
+    counts.get(&tile.canonical_edge(side)) == Some(&1)
+}
+
+fn corners<'a>(tiles: &'a [Tile], counts: &HashMap<u32, usize>) -> Vec<&'a Tile> {
+    tiles
+        .iter()
+        .filter(|tile| {
+            Direction::ALL
+                .iter()
+                .filter(|side| is_outer_edge(counts, tile, **side))
+                .count()
+                == 2
+        })
+        .collect()
+}
+
+/// Orients and places every tile, returning the rows of the finished picture.
+fn assemble(tiles: &[Tile]) -> Option<Vec<Vec<Tile>>> {
+    let side_len = (1..=tiles.len()).find(|n| n * n >= tiles.len())?;
+    if side_len * side_len != tiles.len() {
+        return None;
+    }
+    let counts = edge_counts(tiles);
+    let first = corners(tiles, &counts).into_iter().next()?;
+    // the top left corner must have its unmatched edges facing up and left
+    let first = first.orientations().find(|t| {
+        is_outer_edge(&counts, t, Direction::Up) && is_outer_edge(&counts, t, Direction::Left)
+    })?;
+    let mut used = vec![false; tiles.len()];
+    used[tiles.iter().position(|t| t.id == first.id)?] = true;
+
+    let mut rows: Vec<Vec<Tile>> = vec![];
+    for y in 0..side_len {
+        let mut row: Vec<Tile> = vec![];
+        for x in 0..side_len {
+            if x == 0 && y == 0 {
+                row.push(first.clone());
+                continue;
+            }
+            let left = row.last().map(|t| t.edge(Direction::Right));
+            let above = rows.last().map(|r| r[x].edge(Direction::Down));
+            let fits = |t: &Tile| {
+                left.is_none_or(|e| t.edge(Direction::Left) == e)
+                    && above.is_none_or(|e| t.edge(Direction::Up) == e)
+            };
+            let (index, tile) = tiles
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !used[*i])
+                .find_map(|(i, tile)| tile.orientations().find(|t| fits(t)).map(|t| (i, t)))?;
+            used[index] = true;
+            row.push(tile);
+        }
+        rows.push(row);
+    }
+    Some(rows)
+}
+
+/// Joins the assembled tiles into one image, without their borders.
+fn join(rows: &[Vec<Tile>]) -> Grid<Pixel> {
+    let inner = rows[0][0].image.width() - 2;
+    let size = inner * rows.len();
+    let mut picture = Grid::new(size, size, Pixel::Empty);
+    for (tile_y, row) in rows.iter().enumerate() {
+        for (tile_x, tile) in row.iter().enumerate() {
+            for y in 0..inner {
+                for x in 0..inner {
+                    let pos = Vector2::new(tile_x * inner + x, tile_y * inner + y);
+                    picture[pos] = tile.image[Vector2::new(x + 1, y + 1)];
+                }
+            }
+        }
+    }
+    picture
+}
+
+/// The number of pixels that are part of a sea monster.
+fn sea_monster_pixels(picture: &Grid<Pixel>) -> usize {
+    let monster: Vec<Vector2<usize>> = SEA_MONSTER
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| Vector2::new(x, y))
+        })
+        .collect();
+    let monster_size = Vector2::new(SEA_MONSTER[0].len(), SEA_MONSTER.len());
+    let mut covered = Grid::new(picture.width(), picture.height(), false);
+    for origin in picture.positions() {
+        let fits = picture.contains(origin + monster_size - Vector2::one())
+            && monster
+                .iter()
+                .all(|offset| picture[origin + *offset] == Pixel::Filled);
+        if fits {
+            for offset in monster.iter() {
+                covered[origin + *offset] = true;
+            }
+        }
+    }
+    covered.iter().filter(|c| **c).count()
+}
+
+#[aoc(day20, part1)]
+fn part1(input: &[Tile]) -> Option<u64> {
+    let counts = edge_counts(input);
+    let corners = corners(input, &counts);
+    if corners.len() != 4 {
+        return None;
+    }
+    Some(corners.iter().map(|t| t.id).product())
+}
+
+#[aoc(day20, part2)]
+fn part2(input: &[Tile]) -> Option<usize> {
+    let picture = join(&assemble(input)?);
+    let filled = picture.iter().filter(|p| **p == Pixel::Filled).count();
+    // only the correct orientation has any monsters in it
+    let monsters = Transform2::ALL
+        .iter()
+        .map(|t| sea_monster_pixels(&picture.transformed(*t)))
+        .max()?;
+    Some(filled - monsters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The published 3x3 example.
+    const EXAMPLE: &str = "\
+Tile 2311:
+..##.#..#.
+##..#.....
+#...##..#.
+####.#...#
+##.##.###.
+##...#.###
+.#.#.#..##
+..#....#..
+###...#.#.
+..###..###
+
+Tile 1951:
+#.##...##.
+#.####...#
+.....#..##
+#...######
+.##.#....#
+.###.#####
+###.##.##.
+.###....#.
+..#.#..#.#
+#...##.#..
+
+Tile 1171:
+####...##.
+#..##.#..#
+##.#..#.#.
+.###.####.
+..###.####
+.##....##.
+.#...####.
+#.##.####.
+####..#...
+.....##...
+
+Tile 1427:
+###.##.#..
+.#..#.##..
+.#.##.#..#
+#.#.#.##.#
+....#...##
+...##..##.
+...#.#####
+.#.####.#.
+..#..###.#
+..##.#..#.
+
+Tile 1489:
+##.#.#....
+..##...#..
+.##..##...
+..#...#...
+#####...#.
+#..#.#.#.#
+...#.#.#..
+##.#...##.
+..##.##.##
+###.##.#..
+
+Tile 2473:
+#....####.
+#..#.##...
+#.##..#...
+######.#.#
+.#...#.#.#
+.#########
+.###.#..#.
+########.#
+##...##.#.
+..###.#.#.
+
+Tile 2971:
+..#.#....#
+#...###...
+#.#.###...
+##.##..#..
+.#####..##
+.#..####.#
+#..#.#..#.
+..####.###
+..#.#.###.
+...#.#.#.#
+
+Tile 2729:
+...#.#.#.#
+####.#....
+..#.#.....
+....#..#.#
+.##..##.#.
+.#.####...
+####.#.#..
+##.####...
+##..#.##..
+#.##...##.
+
+Tile 3079:
+#.#.#####.
+.#..######
+..#.......
+######....
+####.#..#.
+.#...#.##.
+#.#####.##
+..#.###...
+..#.......
+..#.###...";
+
+    #[test]
+    fn example() {
+        let tiles = parse_input(EXAMPLE).unwrap();
+        assert_eq!(tiles.len(), 9);
+        assert_eq!(part1(&tiles), Some(20899048083289));
+        assert_eq!(part2(&tiles), Some(273));
+    }
+
+    #[test]
+    fn reports_bad_pixel_line() {
+        let err = parse_input("Tile 1:\n.#\n#.\n\nTile 2:\n..\n.x").unwrap_err();
+        assert_eq!(err.line(), Some(7));
+        assert_eq!(err.columns(), Some(1..2));
+    }
+}
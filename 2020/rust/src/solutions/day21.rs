@@ -0,0 +1,116 @@
+// --- Day 21: Allergen Assessment ---
+// An allergen's ingredient must be in every food that lists the allergen,
+// so intersecting those foods narrows down the candidates.
+
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+struct Food {
+    ingredients: HashSet<String>,
+    allergens: Vec<String>,
+}
+
+impl FromStr for Food {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref FOOD: Regex =
+                Regex::new(r"^([a-z]+(?: [a-z]+)*)(?: \(contains ([a-z]+(?:, [a-z]+)*)\))?$")
+                    .unwrap();
+        }
+        let cap = FOOD.captures(s).ok_or_else(|| {
+            ParseError::expected("ingredients followed by \"(contains <allergens>)\"").with_text(s)
+        })?;
+        let ingredients = cap[1].split(' ').map(String::from).collect();
+        let allergens = match cap.get(2) {
+            Some(allergens) => allergens.as_str().split(", ").map(String::from).collect(),
+            None => vec![],
+        };
+        Ok(Food {
+            ingredients,
+            allergens,
+        })
+    }
+}
+
+#[aoc_generator(day21)]
+fn parse_input(input: &str) -> Result<Vec<Food>, ParseError> {
+    ParseMode::current().parse_lines_with(input, |l| l.parse())
+}
+
+/// For every allergen, the ingredients that could contain it.
+fn candidates(foods: &[Food]) -> BTreeMap<&str, HashSet<&str>> {
+    let mut candidates: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+    for food in foods {
+        let ingredients: HashSet<&str> = food.ingredients.iter().map(|i| i.as_str()).collect();
+        for allergen in food.allergens.iter() {
+            candidates
+                .entry(allergen)
+                .and_modify(|c| c.retain(|i| ingredients.contains(i)))
+                .or_insert_with(|| ingredients.clone());
+        }
+    }
+    candidates
+}
+
+#[aoc(day21, part1)]
+fn part1(input: &[Food]) -> usize {
+    let unsafe_ingredients: HashSet<&str> = candidates(input)
+        .values()
+        .flat_map(|c| c.iter().copied())
+        .collect();
+    input
+        .iter()
+        .flat_map(|f| f.ingredients.iter())
+        .filter(|i| !unsafe_ingredients.contains(i.as_str()))
+        .count()
+}
+
+#[aoc(day21, part2)]
+fn part2(input: &[Food]) -> Option<String> {
+    let mut candidates = candidates(input);
+    let mut resolved: BTreeMap<&str, &str> = BTreeMap::new();
+    // keep pinning down allergens with only one possible ingredient
+    while !candidates.is_empty() {
+        let (allergen, ingredient) = candidates
+            .iter()
+            .find(|(_, c)| c.len() == 1)
+            .map(|(a, c)| (*a, *c.iter().next().unwrap()))?;
+        candidates.remove(allergen);
+        for c in candidates.values_mut() {
+            c.remove(ingredient);
+        }
+        resolved.insert(allergen, ingredient);
+    }
+    // sorted by allergen
+    Some(resolved.values().copied().collect::<Vec<_>>().join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+mxmxvkd kfcds sqjhc nhms (contains dairy, fish)
+trh fvjkl sbzzf mxmxvkd (contains dairy)
+sqjhc fvjkl (contains soy)
+sqjhc mxmxvkd sbzzf (contains fish)";
+
+    #[test]
+    fn example() {
+        let foods = parse_input(EXAMPLE).unwrap();
+        assert_eq!(part1(&foods), 5);
+        assert_eq!(part2(&foods).unwrap(), "mxmxvkd,sqjhc,fvjkl");
+    }
+
+    #[test]
+    fn rejects_unclosed_allergens() {
+        let err = parse_input("abc def (contains soy").unwrap_err();
+        assert_eq!(err.line(), Some(1));
+    }
+}
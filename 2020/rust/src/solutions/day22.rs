@@ -0,0 +1,145 @@
+// --- Day 22: Crab Combat ---
+
+use crate::common::parse_error::ParseError;
+use std::collections::{HashSet, VecDeque};
+
+type Deck = VecDeque<usize>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Player {
+    One,
+    Two,
+}
+
+/// Parses "Player N:" followed by one card per line, starting at `first_line`.
+fn parse_deck(block: &str, player: usize, first_line: usize) -> Result<Deck, ParseError> {
+    let mut lines = block.lines();
+    let header = format!("Player {}:", player);
+    match lines.next() {
+        Some(line) if line == header => {}
+        line => {
+            return Err(ParseError::expected(format!("{:?}", header))
+                .with_text(line.unwrap_or(""))
+                .at_line(first_line))
+        }
+    }
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse().map_err(|e| {
+                ParseError::from(e)
+                    .with_text(line)
+                    .at_line(first_line + i + 1)
+            })
+        })
+        .collect()
+}
+
+#[aoc_generator(day22)]
+fn parse_input(input: &str) -> Result<(Deck, Deck), ParseError> {
+    let mut blocks = input.split("\n\n");
+    let first = blocks.next().unwrap_or("");
+    let second = blocks
+        .next()
+        .ok_or_else(|| ParseError::expected("2 decks separated by a blank line"))?;
+    let one = parse_deck(first, 1, 1)?;
+    let two = parse_deck(second, 2, first.lines().count() + 2)?;
+    Ok((one, two))
+}
+
+fn score(deck: &Deck) -> usize {
+    deck.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, card)| (i + 1) * card)
+        .sum()
+}
+
+/// Plays a game to the end, leaving the winner holding every card.
+fn play(one: &mut Deck, two: &mut Deck, recursive: bool) -> Player {
+    let mut seen = HashSet::new();
+    while !one.is_empty() && !two.is_empty() {
+        // repeating an earlier round ends the game in player 1's favour
+        if recursive && !seen.insert((one.clone(), two.clone())) {
+            return Player::One;
+        }
+        let a = one.pop_front().unwrap();
+        let b = two.pop_front().unwrap();
+        let winner = if recursive && one.len() >= a && two.len() >= b {
+            let mut sub_one = one.iter().take(a).copied().collect();
+            let mut sub_two = two.iter().take(b).copied().collect();
+            play(&mut sub_one, &mut sub_two, true)
+        } else if a > b {
+            Player::One
+        } else {
+            Player::Two
+        };
+        match winner {
+            Player::One => one.extend([a, b].iter()),
+            Player::Two => two.extend([b, a].iter()),
+        }
+    }
+    if one.is_empty() {
+        Player::Two
+    } else {
+        Player::One
+    }
+}
+
+fn winning_score(input: &(Deck, Deck), recursive: bool) -> usize {
+    let (mut one, mut two) = input.clone();
+    match play(&mut one, &mut two, recursive) {
+        Player::One => score(&one),
+        Player::Two => score(&two),
+    }
+}
+
+#[aoc(day22, part1)]
+fn part1(input: &(Deck, Deck)) -> usize {
+    winning_score(input, false)
+}
+
+#[aoc(day22, part2)]
+fn part2(input: &(Deck, Deck)) -> usize {
+    winning_score(input, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Player 1:
+9
+2
+6
+3
+1
+
+Player 2:
+5
+8
+4
+7
+10";
+
+    #[test]
+    fn example() {
+        let decks = parse_input(EXAMPLE).unwrap();
+        assert_eq!(part1(&decks), 306);
+        assert_eq!(part2(&decks), 291);
+    }
+
+    #[test]
+    fn infinite_game_ends() {
+        let decks = parse_input("Player 1:\n43\n19\n\nPlayer 2:\n2\n29\n14").unwrap();
+        let (mut one, mut two) = decks;
+        assert_eq!(play(&mut one, &mut two, true), Player::One);
+    }
+
+    #[test]
+    fn reports_bad_card() {
+        let err = parse_input("Player 1:\n1\n\nPlayer 2:\n2\nx").unwrap_err();
+        assert_eq!(err.line(), Some(6));
+    }
+}
@@ -0,0 +1,143 @@
+// --- Day 23: Crab Cups ---
+// The cups form a ring, stored as a linked list in a Vec where `next[cup]`
+// is the label of the cup clockwise of `cup`. Every move is then O(1).
+
+use crate::common::parse_error::ParseError;
+
+struct Ring {
+    next: Vec<u32>,
+    current: u32,
+}
+
+impl Ring {
+    /// Cups labelled with `labels` in order, followed by every label up to `size`.
+    fn new(labels: &[u32], size: usize) -> Self {
+        let mut next = vec![0; size + 1];
+        let order = labels
+            .iter()
+            .copied()
+            .chain(labels.len() as u32 + 1..=size as u32);
+        let mut previous = None;
+        let mut first = 0;
+        for label in order {
+            match previous {
+                Some(p) => next[p as usize] = label,
+                None => first = label,
+            }
+            previous = Some(label);
+        }
+        if let Some(last) = previous {
+            next[last as usize] = first;
+        }
+        Ring {
+            next,
+            current: first,
+        }
+    }
+
+    fn max_label(&self) -> u32 {
+        self.next.len() as u32 - 1
+    }
+
+    fn play_move(&mut self) {
+        let a = self.next[self.current as usize];
+        let b = self.next[a as usize];
+        let c = self.next[b as usize];
+        // cut the 3 picked up cups out of the ring
+        self.next[self.current as usize] = self.next[c as usize];
+        let mut destination = self.current;
+        loop {
+            destination = if destination == 1 {
+                self.max_label()
+            } else {
+                destination - 1
+            };
+            if destination != a && destination != b && destination != c {
+                break;
+            }
+        }
+        // and splice them back in after the destination
+        self.next[c as usize] = self.next[destination as usize];
+        self.next[destination as usize] = a;
+        self.current = self.next[self.current as usize];
+    }
+
+    /// Labels clockwise from (and not including) `label`.
+    fn after(&self, label: u32) -> impl Iterator<Item = u32> + '_ {
+        std::iter::successors(Some(self.next[label as usize]), move |l| {
+            Some(self.next[*l as usize])
+        })
+        .take_while(move |l| *l != label)
+    }
+}
+
+#[aoc_generator(day23)]
+fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    let input = input.trim();
+    let labels = input
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c.to_digit(10) {
+            Some(d) if d > 0 => Ok(d),
+            _ => Err(ParseError::expected("a cup labelled 1-9")
+                .with_text(c.to_string())
+                .at_columns(i..i + 1)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // the labels must be exactly 1..=n for the ring to be well formed
+    let mut sorted = labels.clone();
+    sorted.sort_unstable();
+    if sorted.is_empty() || sorted.iter().zip(1..).any(|(a, b)| *a != b) {
+        return Err(
+            ParseError::expected("each label from 1 up to the number of cups once")
+                .with_text(input),
+        );
+    }
+    Ok(labels)
+}
+
+#[aoc(day23, part1)]
+fn part1(input: &[u32]) -> String {
+    let mut ring = Ring::new(input, input.len());
+    for _ in 0..100 {
+        ring.play_move();
+    }
+    ring.after(1).map(|l| l.to_string()).collect()
+}
+
+#[aoc(day23, part2)]
+fn part2(input: &[u32]) -> u64 {
+    let mut ring = Ring::new(input, 1_000_000);
+    for _ in 0..10_000_000 {
+        ring.play_move();
+    }
+    ring.after(1).take(2).map(u64::from).product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let cups = parse_input("389125467").unwrap();
+        assert_eq!(part1(&cups), "67384529");
+        assert_eq!(part2(&cups), 149245887792);
+    }
+
+    #[test]
+    fn ten_moves() {
+        let mut ring = Ring::new(&parse_input("389125467").unwrap(), 9);
+        for _ in 0..10 {
+            ring.play_move();
+        }
+        let labels: String = ring.after(1).map(|l| l.to_string()).collect();
+        assert_eq!(labels, "92658374");
+    }
+
+    #[test]
+    fn rejects_missing_labels() {
+        assert!(parse_input("3891").is_err());
+        assert_eq!(parse_input("38a").unwrap_err().columns(), Some(2..3));
+    }
+}
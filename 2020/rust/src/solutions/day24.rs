@@ -0,0 +1,108 @@
+// --- Day 24: Lobby Layout ---
+// Tiles are addressed with axial coordinates, see `HexDirection`.
+
+use crate::common::direction::HexDirection;
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
+use crate::common::vector2::Vector2;
+use std::collections::{HashMap, HashSet};
+
+type Tile = Vector2<i32>;
+
+#[aoc_generator(day24)]
+fn parse_input(input: &str) -> Result<Vec<Vec<HexDirection>>, ParseError> {
+    ParseMode::current().parse_lines_with(input, HexDirection::parse_sequence)
+}
+
+/// The tiles that are black side up after following every path from the reference tile.
+fn black_tiles(paths: &[Vec<HexDirection>]) -> HashSet<Tile> {
+    let mut black = HashSet::new();
+    for path in paths {
+        let tile = path.iter().fold(Tile::zero(), |pos, dir| pos + dir.axial());
+        // flip it
+        if !black.remove(&tile) {
+            black.insert(tile);
+        }
+    }
+    black
+}
+
+fn next_day(black: &HashSet<Tile>) -> HashSet<Tile> {
+    let mut black_neighbours: HashMap<Tile, usize> = HashMap::new();
+    for tile in black {
+        for dir in HexDirection::ALL.iter() {
+            *black_neighbours.entry(*tile + dir.axial()).or_insert(0) += 1;
+        }
+    }
+    black_neighbours
+        .into_iter()
+        .filter(|(tile, count)| match black.contains(tile) {
+            true => *count == 1 || *count == 2,
+            false => *count == 2,
+        })
+        .map(|(tile, _)| tile)
+        .collect()
+}
+
+#[aoc(day24, part1)]
+fn part1(input: &[Vec<HexDirection>]) -> usize {
+    black_tiles(input).len()
+}
+
+#[aoc(day24, part2)]
+fn part2(input: &[Vec<HexDirection>]) -> usize {
+    let mut black = black_tiles(input);
+    for _ in 0..100 {
+        black = next_day(&black);
+    }
+    black.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+nwsesesese
+nenee
+neswne
+ww
+enw
+wnew
+wenesw
+seneswswenw
+neesesw
+neeswe
+nenwse
+swswnwnesw
+enww
+nesesw
+senenwsew
+swnwsenwsee
+ww
+wnew";
+
+    #[test]
+    fn example() {
+        let paths = parse_input(EXAMPLE).unwrap();
+        assert_eq!(part1(&paths), 8);
+        assert_eq!(part2(&paths), 2267);
+    }
+
+    #[test]
+    fn first_days() {
+        let mut black = black_tiles(&parse_input(EXAMPLE).unwrap());
+        black = next_day(&black);
+        assert_eq!(black.len(), 12);
+        for _ in 1..10 {
+            black = next_day(&black);
+        }
+        assert_eq!(black.len(), 56);
+    }
+
+    #[test]
+    fn loop_returns_to_reference_tile() {
+        let paths = parse_input("nwwswee").unwrap();
+        assert!(black_tiles(&paths).contains(&Tile::zero()));
+    }
+}
@@ -0,0 +1,47 @@
+// --- Day 25: Combo Breaker ---
+// Each public key is 7^loop_size mod 20201227, a discrete logarithm.
+
+use crate::common::modmath::{discrete_log, pow_mod};
+use crate::common::parse_error::ParseError;
+use crate::common::parse_mode::ParseMode;
+
+const MODULUS: u64 = 20201227;
+const SUBJECT: u64 = 7;
+
+fn transform(subject: u64, loop_size: u64) -> u64 {
+    pow_mod(subject, loop_size, MODULUS)
+}
+
+/// The loop size that transforms `SUBJECT` into `public_key`, if there is one.
+fn loop_size(public_key: u64) -> Option<u64> {
+    discrete_log(SUBJECT, public_key, MODULUS)
+}
+
+#[aoc_generator(day25)]
+fn parse_input(input: &str) -> Result<(u64, u64), ParseError> {
+    match ParseMode::current().parse_lines_with(input, |l| Ok(l.parse()?))?[..] {
+        [card, door] => Ok((card, door)),
+        _ => Err(ParseError::expected("2 public keys, one per line")),
+    }
+}
+
+#[aoc(day25, part1)]
+fn part1(input: &(u64, u64)) -> Option<u64> {
+    let (card, door) = *input;
+    Some(transform(door, loop_size(card)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        assert_eq!(loop_size(5764801), Some(8));
+        assert_eq!(loop_size(17807724), Some(11));
+        assert_eq!(
+            part1(&parse_input("5764801\n17807724").unwrap()),
+            Some(14897079)
+        );
+    }
+}
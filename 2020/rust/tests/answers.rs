@@ -0,0 +1,75 @@
+//! Checks the real puzzle inputs against the answers recorded in
+//! `input/2020/answers.txt`, so refactors can't quietly change a result.
+//!
+//! This is slow in a debug build, so it only runs when asked for:
+//!
+//! ```shell
+//! cargo test --release --test answers -- --ignored
+//! ```
+
+use rust_2020::runner::{run, solver};
+use std::fs;
+use std::path::Path;
+
+/// A line of the answers file, "<day> <part> <answer>".
+fn parse_answer(line: &str) -> Option<(u32, u32, &str)> {
+    let mut parts = line.split_whitespace();
+    let day = parts.next()?.parse().ok()?;
+    let part = parts.next()?.parse().ok()?;
+    let answer = parts.next()?;
+    match parts.next() {
+        Some(_) => None,
+        None => Some((day, part, answer)),
+    }
+}
+
+#[test]
+#[ignore = "slow, run with --ignored in release mode"]
+fn recorded_answers() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("input/2020");
+    let answers = match fs::read_to_string(dir.join("answers.txt")) {
+        Ok(answers) => answers,
+        Err(_) => {
+            eprintln!("no recorded answers, skipping");
+            return;
+        }
+    };
+    let mut failures = vec![];
+    let mut checked = 0;
+    for (i, line) in answers.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (day, part, expected) = match parse_answer(line) {
+            Some(answer) => answer,
+            None => {
+                failures.push(format!("line {}: can't read {:?}", i + 1, line));
+                continue;
+            }
+        };
+        let solver = match solver(day, part) {
+            Some(solver) => solver,
+            None => {
+                failures.push(format!("day {} part {}: no such solution", day, part));
+                continue;
+            }
+        };
+        // inputs are personal, so not everyone will have every day
+        let input = match fs::read_to_string(dir.join(format!("day{}.txt", day))) {
+            Ok(input) => input,
+            Err(_) => continue,
+        };
+        checked += 1;
+        match run(solver, &input) {
+            Ok(outcome) if outcome.answer == expected => {}
+            Ok(outcome) => failures.push(format!(
+                "day {} part {}: expected {}, got {}",
+                day, part, expected, outcome.answer
+            )),
+            Err(e) => failures.push(format!("day {} part {}: {}", day, part, e)),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    eprintln!("{} recorded answers match", checked);
+}
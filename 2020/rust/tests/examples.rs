@@ -0,0 +1,75 @@
+//! Runs the examples from each puzzle through the generators and solvers,
+//! checking the answers given in the puzzle text.
+//!
+//! Examples live in `tests/examples`, one file per day, or one per part
+//! where the parts use different examples.
+
+use rust_2020::runner::{run, solver};
+
+macro_rules! example {
+    ($($(#[$attr:meta])* $name:ident: $day:literal, $part:literal, $file:literal => $answer:literal;)*) => {
+        $(
+            #[test]
+            $(#[$attr])*
+            fn $name() {
+                let input = include_str!(concat!("examples/", $file));
+                let solver = solver($day, $part).expect("no such solution");
+                match run(solver, input) {
+                    Ok(outcome) => assert_eq!(outcome.answer, $answer),
+                    Err(e) => panic!("{} failed: {}", $file, e),
+                }
+            }
+        )*
+    };
+}
+
+example! {
+    day01_part1: 1, 1, "day01.txt" => "514579";
+    day01_part2: 1, 2, "day01.txt" => "241861950";
+    day02_part1: 2, 1, "day02.txt" => "2";
+    day02_part2: 2, 2, "day02.txt" => "1";
+    day03_part1: 3, 1, "day03.txt" => "7";
+    day03_part2: 3, 2, "day03.txt" => "336";
+    day04_part1: 4, 1, "day04.txt" => "2";
+    day04_part2: 4, 2, "day04.txt" => "2";
+    day05_part1: 5, 1, "day05.txt" => "820";
+    day06_part1: 6, 1, "day06.txt" => "11";
+    day06_part2: 6, 2, "day06.txt" => "6";
+    day07_part1: 7, 1, "day07.txt" => "4";
+    day07_part2: 7, 2, "day07.txt" => "32";
+    day08_part1: 8, 1, "day08.txt" => "5";
+    day08_part2: 8, 2, "day08.txt" => "8";
+    // the real input uses a preamble of 25, so this is 1-25 followed by the
+    // numbers the puzzle text checks against it
+    day09_part1: 9, 1, "day09.txt" => "100";
+    day09_part2: 9, 2, "day09.txt" => "74";
+    day10_part1: 10, 1, "day10.txt" => "35";
+    day10_part2: 10, 2, "day10.txt" => "8";
+    day11_part1: 11, 1, "day11.txt" => "37";
+    day11_part2: 11, 2, "day11.txt" => "26";
+    day12_part1: 12, 1, "day12.txt" => "25";
+    day12_part2: 12, 2, "day12.txt" => "286";
+    day13_part1: 13, 1, "day13.txt" => "295";
+    day13_part2: 13, 2, "day13.txt" => "1068781";
+    day14_part1: 14, 1, "day14-part1.txt" => "165";
+    day14_part2: 14, 2, "day14-part2.txt" => "208";
+    day15_part1: 15, 1, "day15.txt" => "436";
+    day15_part2: 15, 2, "day15.txt" => "175594";
+    day16_part1: 16, 1, "day16-part1.txt" => "71";
+    day17_part1: 17, 1, "day17.txt" => "112";
+    day17_part2: 17, 2, "day17.txt" => "848";
+    day18_part1: 18, 1, "day18.txt" => "26406";
+    day18_part2: 18, 2, "day18.txt" => "694122";
+    day19_part1: 19, 1, "day19-part1.txt" => "2";
+    day20_part1: 20, 1, "day20.txt" => "20899048083289";
+    day20_part2: 20, 2, "day20.txt" => "273";
+    day21_part1: 21, 1, "day21.txt" => "5";
+    day21_part2: 21, 2, "day21.txt" => "mxmxvkd,sqjhc,fvjkl";
+    day22_part1: 22, 1, "day22.txt" => "306";
+    day22_part2: 22, 2, "day22.txt" => "291";
+    day23_part1: 23, 1, "day23.txt" => "67384529";
+    day23_part2: 23, 2, "day23.txt" => "149245887792";
+    day24_part1: 24, 1, "day24.txt" => "8";
+    day24_part2: 24, 2, "day24.txt" => "2267";
+    day25_part1: 25, 1, "day25.txt" => "14897079";
+}